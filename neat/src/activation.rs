@@ -2,6 +2,8 @@ use std::cmp::max;
 
 use float_ord::FloatOrd;
 
+use params::Params;
+
 /// The activation function for each neuron.
 #[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, PartialOrd)]
 pub enum Activation {
@@ -13,15 +15,60 @@ pub enum Activation {
 
     /// tanh(x)
     Tanh,
+
+    /// sin(x)
+    Sine,
+
+    /// e ^ (-x^2)
+    Gaussian,
+
+    /// |x|
+    Abs,
+
+    /// 1 if x > 0, else 0
+    Step,
 }
 
 impl Activation {
+    /// Every activation function. The ones beyond `DIRECT` are mainly useful
+    /// for CPPNs.
+    pub const ALL: &'static [Activation] = &[
+        Activation::ReLU,
+        Activation::Sigmoid,
+        Activation::Tanh,
+        Activation::Sine,
+        Activation::Gaussian,
+        Activation::Abs,
+        Activation::Step,
+    ];
+
+    /// The activation functions used by networks that are evolved directly.
+    pub const DIRECT: &'static [Activation] =
+        &[Activation::ReLU, Activation::Sigmoid, Activation::Tanh];
+
+    /// Returns the activation functions that mutations may choose from.
+    pub fn palette(params: &Params) -> &'static [Activation] {
+        if params.cppn {
+            Activation::ALL
+        } else {
+            Activation::DIRECT
+        }
+    }
+
     /// Calculates the activation function for the given value.
     pub fn calculate(self, x: f32) -> f32 {
         match self {
             Activation::ReLU => max(FloatOrd(x), FloatOrd(0.0)).0,
             Activation::Sigmoid => (2.0 / (1.0 + (-4.9 * x).exp())) - 1.0,
             Activation::Tanh => x.tanh(),
+            Activation::Sine => x.sin(),
+            Activation::Gaussian => (-x * x).exp(),
+            Activation::Abs => x.abs(),
+            Activation::Step => if x > 0.0 {
+                1.0
+            } else {
+                0.0
+            },
         }
     }
 }
@@ -2,7 +2,7 @@ use std::cmp::max;
 
 use rand::Rng;
 
-use genome::{Gene, Genome};
+use genome::{Gene, Genome, NodeGene};
 use params::Params;
 use species::Species;
 
@@ -28,48 +28,134 @@ pub enum GeneClass {
     DisjointRight,
 }
 
-/// Classifies the genes of each of two organisms.
-pub fn classify_genes(g1: &[Gene], g2: &[Gene]) -> Vec<GeneClass> {
-    let inno_pos = |genes: &[Gene], inno: usize| {
-        genes.iter().position(|g| g.innovation == inno)
-    };
-    let max_inno = |genes: &[Gene]| {
-        genes
-            .iter()
-            .map(|g| g.innovation)
-            .max()
-            .unwrap_or(0)
-    };
-
-    let max_l = max_inno(g1);
-    let max_r = max_inno(g2);
-    let max_innovation = max(max_l, max_r);
+/// Aligns the genes of each of two organisms by innovation number, pairing
+/// each gene's classification with the gene from each side, if present.
+///
+/// Both gene lists must be sorted by innovation number, as a genome's genes
+/// always are. The alignment is then a single merge over the two lists.
+pub fn align_genes<'a>(
+    g1: &'a [Gene],
+    g2: &'a [Gene],
+) -> Vec<(GeneClass, Option<&'a Gene>, Option<&'a Gene>)> {
+    let max_l = g1.last().map(|g| g.innovation).unwrap_or(0);
+    let max_r = g2.last().map(|g| g.innovation).unwrap_or(0);
 
-    let mut gene_classes = Vec::new();
-    for inno in 0..max_innovation {
-        match (inno_pos(g1, inno), inno_pos(g2, inno)) {
-            (Some(l), Some(r)) => {
-                let weight_diff = (g1[l].weight - g2[r].weight).abs();
-                gene_classes.push(GeneClass::Matching(weight_diff));
+    let mut aligned = Vec::with_capacity(max(g1.len(), g2.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < g1.len() || j < g2.len() {
+        let l = g1.get(i);
+        let r = g2.get(j);
+        match (l, r) {
+            (Some(l), Some(r)) if l.innovation == r.innovation => {
+                let class = GeneClass::Matching((l.weight - r.weight).abs());
+                aligned.push((class, Some(l), Some(r)));
+                i += 1;
+                j += 1;
             }
-            (Some(_), None) => {
-                gene_classes.push(if inno > max_r {
+            (Some(l), Some(r)) if l.innovation < r.innovation => {
+                aligned.push((GeneClass::DisjointLeft, Some(l), None));
+                i += 1;
+            }
+            (Some(l), None) => {
+                let class = if l.innovation > max_r {
                     GeneClass::ExcessLeft
                 } else {
                     GeneClass::DisjointLeft
-                });
+                };
+                aligned.push((class, Some(l), None));
+                i += 1;
             }
-            (None, Some(_)) => {
-                gene_classes.push(if inno > max_l {
+            (_, Some(r)) => {
+                let class = if r.innovation > max_l {
                     GeneClass::ExcessRight
                 } else {
                     GeneClass::DisjointRight
-                });
+                };
+                aligned.push((class, None, Some(r)));
+                j += 1;
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    aligned
+}
+
+/// Classifies the genes of each of two organisms.
+pub fn classify_genes(g1: &[Gene], g2: &[Gene]) -> Vec<GeneClass> {
+    align_genes(g1, g2)
+        .into_iter()
+        .map(|(class, _, _)| class)
+        .collect()
+}
+
+/// Produces a child from two parents and their fitnesses.
+///
+/// Matching genes are inherited randomly from either parent, while disjoint
+/// and excess genes are inherited only from the fitter parent. If the parents
+/// are equally fit, the first is treated as the fitter one. A matching gene
+/// that is disabled in either parent is disabled in the child with a chance of
+/// `params.inherit_disabled_rate`.
+///
+/// Node genes are inherited from the fitter parent, except that a node gene
+/// both parents have is inherited randomly from either.
+pub fn crossover<R: Rng>(
+    rng: &mut R,
+    (g1, f1): (&Genome, f32),
+    (g2, f2): (&Genome, f32),
+    params: &Params,
+) -> Genome {
+    let (g1, g2) = if f2 > f1 { (g2, g1) } else { (g1, g2) };
+
+    let genes = align_genes(&g1.genes, &g2.genes)
+        .into_iter()
+        .filter_map(|aligned| match aligned {
+            (GeneClass::Matching(_), Some(l), Some(r)) => {
+                let mut gene = if rng.gen() { l.clone() } else { r.clone() };
+                gene.enabled = if l.enabled && r.enabled {
+                    true
+                } else {
+                    rng.next_f32() >= params.inherit_disabled_rate
+                };
+                Some(gene)
+            }
+            (GeneClass::ExcessLeft, Some(l), None)
+            | (GeneClass::DisjointLeft, Some(l), None) => Some(l.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let nodes = g1.nodes
+        .iter()
+        .map(|l| match g2.node(l.id) {
+            Some(r) if rng.gen() => r.clone(),
+            _ => l.clone(),
+        })
+        .collect();
+
+    Genome { nodes, genes }
+}
+
+/// Computes the difference between the node genes two organisms have in
+/// common, as the mean of the absolute difference between their biases plus
+/// one if their activation functions differ.
+fn node_difference(n1: &[NodeGene], n2: &[NodeGene]) -> f32 {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for l in n1 {
+        if let Some(r) = n2.iter().find(|r| r.id == l.id) {
+            sum += (l.bias - r.bias).abs();
+            if l.activation != r.activation {
+                sum += 1.0;
             }
-            (None, None) => {}
+            count += 1;
         }
     }
-    gene_classes
+
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
 }
 
 /// Computes the difference between two organisms.
@@ -97,52 +183,107 @@ pub fn difference(g1: &Genome, g2: &Genome, params: &Params) -> f32 {
     e /= n;
     d /= n;
 
-    params.c1 * e + params.c2 * d + params.c3 * w
+    let nd = node_difference(&g1.nodes, &g2.nodes);
+
+    params.c1 * e + params.c2 * d + params.c3 * w + params.c4 * nd
 }
 
 /// Classifies new organisms into species based on the previous species.
 ///
-/// `organisms` and `fitnesses` must have the same length. Note that this will
-/// panic if any empty species is provided. Don't do that.
-pub fn classify_species<R: Rng>(
-    rng: &mut R,
+/// Each organism joins the first species whose representative it is close
+/// enough to, or founds a new species with an ID taken from `next_id`. Species
+/// that end up with no members are dropped.
+pub fn classify_species(
     organisms: Vec<Genome>,
     params: &Params,
-    previous_species: &[Species],
+    previous_species: Vec<Species>,
+    next_id: &mut usize,
+    generation: usize,
 ) -> Vec<Species> {
-    let mut species_count = previous_species.len();
-    let species_assignments = {
-        let mut exemplars = previous_species
-            .iter()
-            .map(|species| rng.choose(&species.0).unwrap())
-            .collect::<Vec<_>>();
+    let mut species = previous_species;
+    for s in species.iter_mut() {
+        s.members.clear();
+    }
 
-        organisms
+    for genome in organisms {
+        let i = species
             .iter()
-            .map(|genome| {
-                exemplars
-                    .iter()
-                    .position(|exemplar| {
-                        difference(exemplar, genome, params)
-                            < params.delta_cutoff
-                    })
-                    .unwrap_or_else(|| {
-                        let n = species_count;
-                        exemplars.push(genome);
-                        species_count += 1;
-                        n
-                    })
+            .position(|s| {
+                difference(&s.representative, &genome, params)
+                    < params.delta_cutoff
             })
-            .collect::<Vec<_>>()
-    };
+            .unwrap_or_else(|| {
+                species.push(Species::new(
+                    *next_id,
+                    genome.clone(),
+                    generation,
+                ));
+                *next_id += 1;
+                species.len() - 1
+            });
+        species[i].members.push(genome);
+    }
 
-    let mut species = vec![Species::empty(); species_count];
-    organisms
-        .into_iter()
-        .enumerate()
-        .for_each(|(i, o)| {
-            species[species_assignments[i]].0.push(o);
-        });
-    species.retain(|s| s.0.len() > 0);
+    species.retain(|s| s.len() > 0);
     species
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::XorShiftRng;
+
+    use fixtures::{gene, params};
+    use genome::Genome;
+
+    use super::{classify_genes, crossover, GeneClass};
+
+    #[test]
+    fn classify_genes_aligns_by_innovation() {
+        let g1 = vec![gene(1, 0, 1), gene(2, 0, 2), gene(3, 0, 6)];
+        let g2 = vec![gene(1, 0, 1), gene(4, 0, 3), gene(2, 0, 4)];
+        assert_eq!(
+            classify_genes(&g1, &g2),
+            vec![
+                GeneClass::Matching(0.0),
+                GeneClass::DisjointLeft,
+                GeneClass::DisjointRight,
+                GeneClass::DisjointRight,
+                GeneClass::ExcessLeft,
+            ]
+        );
+        assert_eq!(
+            classify_genes(&g2, &[]),
+            vec![GeneClass::ExcessLeft; 3]
+        );
+        assert_eq!(classify_genes(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn crossover_takes_unmatched_from_fitter() {
+        let g1 = Genome {
+            nodes: vec![],
+            genes: vec![gene(1, 0, 1), gene(2, 0, 2), gene(3, 0, 5)],
+        };
+        let g2 = Genome {
+            nodes: vec![],
+            genes: vec![gene(1, 0, 1), gene(4, 0, 3), gene(3, 5, 4)],
+        };
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let child = crossover(&mut rng, (&g1, 2.0), (&g2, 1.0), &params());
+        let innos = child
+            .genes
+            .iter()
+            .map(|g| g.innovation)
+            .collect::<Vec<_>>();
+        assert_eq!(innos, vec![1, 2, 5]);
+
+        let child = crossover(&mut rng, (&g1, 1.0), (&g2, 2.0), &params());
+        let innos = child
+            .genes
+            .iter()
+            .map(|g| g.innovation)
+            .collect::<Vec<_>>();
+        assert_eq!(innos, vec![1, 3, 4]);
+    }
+}
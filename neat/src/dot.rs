@@ -0,0 +1,124 @@
+//! Export of genomes and networks to Graphviz's DOT language.
+
+use std::fmt::Write;
+
+use activation::Activation;
+use genome::Genome;
+use params::Params;
+
+impl Genome {
+    /// Renders the genome as a Graphviz graph. Inputs are labelled with the
+    /// given labels, falling back to their index if there are too few.
+    ///
+    /// Edges are blue for positive weights and red for negative ones, and are
+    /// thicker the larger the weight's magnitude. Disabled genes are dashed.
+    pub fn to_dot(&self, params: &Params, input_labels: &[&str]) -> String {
+        let mut dot = String::new();
+        write_header(&mut dot, params.inputs, params.outputs, input_labels);
+        for n in self.nodes(params) {
+            if n >= params.first_hidden() {
+                let node = self.node_or_default(n, params);
+                write_hidden(&mut dot, n, node.bias, node.activation);
+            }
+        }
+        for gene in &self.genes {
+            let style = if gene.enabled { "solid" } else { "dashed" };
+            write_edge(&mut dot, gene.from, gene.to, gene.weight, style);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Writes the start of a graph, along with its input and output nodes.
+pub fn write_header(
+    dot: &mut String,
+    inputs: usize,
+    outputs: usize,
+    input_labels: &[&str],
+) {
+    dot.push_str("digraph {\n    rankdir=LR;\n");
+
+    dot.push_str("    { rank=source;\n");
+    for i in 0..inputs {
+        let label = input_labels
+            .get(i)
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| format!("input {}", i));
+        writeln!(dot, "        {} [label={:?}, shape=box];", outputs + i, label)
+            .unwrap();
+    }
+    dot.push_str("    }\n");
+
+    dot.push_str("    { rank=sink;\n");
+    for i in 0..outputs {
+        let label = if outputs == 1 {
+            "output".to_string()
+        } else {
+            format!("output {}", i)
+        };
+        writeln!(dot, "        {} [label={:?}, shape=doublecircle];", i, label)
+            .unwrap();
+    }
+    dot.push_str("    }\n");
+}
+
+/// Writes a hidden node, labelled with its ID, bias and activation function.
+pub fn write_hidden(
+    dot: &mut String,
+    n: usize,
+    bias: f32,
+    activation: Activation,
+) {
+    let label = format!("{}\n{} {:+.2}", n, activation, bias);
+    writeln!(dot, "    {} [label={:?}];", n, label).unwrap();
+}
+
+/// Writes an edge, coloured and sized by its weight.
+pub fn write_edge(
+    dot: &mut String,
+    from: usize,
+    to: usize,
+    weight: f32,
+    style: &str,
+) {
+    let color = if weight < 0.0 { "red" } else { "blue" };
+    let width = 0.5 + weight.abs().min(4.0);
+    writeln!(
+        dot,
+        "    {} -> {} [color={}, penwidth={:.2}, style={}, label=\"{:.2}\"];",
+        from, to, color, width, style, weight
+    ).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use fixtures::{params, weighted};
+    use genome::Genome;
+
+    #[test]
+    fn genome_to_dot() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![
+            weighted(1, 0, 1.0, 1),
+            weighted(1, 5, -3.0, 2),
+            weighted(5, 0, 0.5, 3),
+        ];
+        genome.genes[0].enabled = false;
+
+        let dot = genome.to_dot(&params, &["position", "pipe x"]);
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("1 [label=\"position\", shape=box];"));
+        assert!(dot.contains("4 [label=\"input 3\", shape=box];"));
+        assert!(dot.contains("0 [label=\"output\", shape=doublecircle];"));
+        assert!(dot.contains("5 [label=\"5\\nSigmoid +0.00\"];"));
+        assert!(dot.contains("1 -> 0 [color=blue, penwidth=1.50, style=dash"));
+        assert!(dot.contains("1 -> 5 [color=red, penwidth=3.50, style=solid"));
+
+        let dot = genome.build_network(&params).to_dot(&[]);
+        assert!(dot.contains("1 [label=\"input 0\", shape=box];"));
+        assert!(dot.contains("5 -> 0 [color=blue"));
+        assert!(!dot.contains("1 -> 0"));
+    }
+}
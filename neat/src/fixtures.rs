@@ -0,0 +1,32 @@
+//! Values shared by the unit tests.
+
+use structopt::StructOpt;
+
+use genome::Gene;
+use params::Params;
+
+/// Returns the default parameters, as if no options were given.
+pub fn params() -> Params {
+    Params::from_clap(&Params::clap().get_matches_from(vec!["neat"]))
+}
+
+/// Returns an enabled gene with a weight of one.
+pub fn gene(from: usize, to: usize, innovation: usize) -> Gene {
+    weighted(from, to, 1.0, innovation)
+}
+
+/// Returns an enabled gene with the given weight.
+pub fn weighted(
+    from: usize,
+    to: usize,
+    weight: f32,
+    innovation: usize,
+) -> Gene {
+    Gene {
+        from,
+        to,
+        enabled: true,
+        weight,
+        innovation,
+    }
+}
@@ -0,0 +1,380 @@
+use std::cmp::{max, min};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crossover::align_genes;
+use genome::{Gene, Genome};
+use params::Params;
+
+/// A way in which a genome can be invalid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GenomeError {
+    /// Two genes have the same innovation number.
+    DuplicateInnovation(usize),
+
+    /// A gene's innovation number is lower than the previous gene's. The
+    /// parameters are the previous and current innovation numbers.
+    NonIncreasingInnovation(usize, usize),
+
+    /// Two genes connect the same pair of nodes. The parameters are the
+    /// innovation numbers of both genes, in increasing order.
+    DuplicateConnection(usize, usize),
+
+    /// The gene with the given innovation number leads into an input node.
+    EdgeIntoInput(usize),
+
+    /// The gene with the given innovation number originates from an output
+    /// node.
+    EdgeOutOfOutput(usize),
+
+    /// The hidden node with the given ID has a node gene, but no connections.
+    DanglingHiddenNode(usize),
+
+    /// There is more than one node gene for the node with the given ID.
+    DuplicateNode(usize),
+
+    /// There is a node gene for the input node with the given ID.
+    InputNodeGene(usize),
+
+    /// The gene with the given innovation number is part of a cycle, but the
+    /// network is not recurrent.
+    Cycle(usize),
+
+    /// The gene with the given innovation number has a NaN weight.
+    NanWeight(usize),
+}
+
+impl Display for GenomeError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            GenomeError::DuplicateInnovation(i) => {
+                write!(fmt, "innovation {} appears more than once", i)
+            }
+            GenomeError::NonIncreasingInnovation(prev, i) => write!(
+                fmt,
+                "innovation {} comes after innovation {}",
+                i, prev
+            ),
+            GenomeError::DuplicateConnection(i, j) => write!(
+                fmt,
+                "innovations {} and {} connect the same nodes",
+                i, j
+            ),
+            GenomeError::EdgeIntoInput(i) => {
+                write!(fmt, "innovation {} leads into an input", i)
+            }
+            GenomeError::EdgeOutOfOutput(i) => {
+                write!(fmt, "innovation {} comes from an output", i)
+            }
+            GenomeError::DanglingHiddenNode(n) => {
+                write!(fmt, "hidden node {} has no connections", n)
+            }
+            GenomeError::DuplicateNode(n) => {
+                write!(fmt, "node {} has more than one node gene", n)
+            }
+            GenomeError::InputNodeGene(n) => {
+                write!(fmt, "input node {} has a node gene", n)
+            }
+            GenomeError::Cycle(i) => {
+                write!(fmt, "innovation {} is part of a cycle", i)
+            }
+            GenomeError::NanWeight(i) => {
+                write!(fmt, "innovation {} has a NaN weight", i)
+            }
+        }
+    }
+}
+
+impl Error for GenomeError {
+    fn description(&self) -> &str {
+        "invalid genome"
+    }
+}
+
+impl Genome {
+    /// Checks that this is a valid genome with the input and output layout
+    /// given by the parameters. A valid genome has genes in strictly
+    /// increasing order of innovation number, at most one gene for each pair
+    /// of nodes, no connections into inputs or out of outputs, at most one node
+    /// gene for each node and none for inputs, no hidden nodes without
+    /// connections, no NaN weights, and unless `params.recurrent` is set, no
+    /// cycles. Orphans, which lack enabled incoming or outgoing connections,
+    /// are valid.
+    ///
+    /// Checking for cycles is quadratic in the number of genes; see
+    /// `check_since` for a cheaper check after a mutation.
+    pub fn check(&self, params: &Params) -> Result<(), GenomeError> {
+        self.check_order()?;
+        self.check_node_genes(params)?;
+
+        let mut pairs = HashSet::new();
+        for gene in &self.genes {
+            check_gene(gene, params)?;
+            if !pairs.insert((gene.from, gene.to)) {
+                let other = self.genes
+                    .iter()
+                    .find(|g| g.from == gene.from && g.to == gene.to)
+                    .unwrap();
+                return Err(GenomeError::DuplicateConnection(
+                    other.innovation,
+                    gene.innovation,
+                ));
+            }
+        }
+
+        for n in self.nodes(params) {
+            self.check_node(n, params)?;
+        }
+
+        if !params.recurrent {
+            for gene in &self.genes {
+                if self.creates_cycle_without(gene) {
+                    return Err(GenomeError::Cycle(gene.innovation));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that this genome is valid, assuming it was derived from
+    /// `parent` and that `parent` is valid. Only genes that were added,
+    /// changed or removed since the parent, and the nodes they connect, are
+    /// checked for anything more expensive than their order.
+    pub fn check_since(
+        &self,
+        parent: &Genome,
+        params: &Params,
+    ) -> Result<(), GenomeError> {
+        self.check_order()?;
+        self.check_node_genes(params)?;
+
+        let mut nodes = self.nodes
+            .iter()
+            .filter(|n| parent.node(n.id).is_none())
+            .map(|n| n.id)
+            .collect::<HashSet<_>>();
+        for aligned in align_genes(&parent.genes, &self.genes) {
+            match aligned {
+                (_, Some(old), Some(new)) => {
+                    if old != new {
+                        check_gene(new, params)?;
+                    }
+                }
+                (_, None, Some(new)) => {
+                    check_gene(new, params)?;
+                    if let Some(other) = self.genes.iter().find(|g| {
+                        g.from == new.from && g.to == new.to && g != &new
+                    }) {
+                        return Err(GenomeError::DuplicateConnection(
+                            min(other.innovation, new.innovation),
+                            max(other.innovation, new.innovation),
+                        ));
+                    }
+                    if !params.recurrent && self.creates_cycle_without(new) {
+                        return Err(GenomeError::Cycle(new.innovation));
+                    }
+                    nodes.insert(new.from);
+                    nodes.insert(new.to);
+                }
+                (_, Some(old), None) => {
+                    nodes.insert(old.from);
+                    nodes.insert(old.to);
+                }
+                (_, None, None) => {}
+            }
+        }
+
+        for n in nodes {
+            let present = self.node(n).is_some()
+                || self.genes.iter().any(|g| g.from == n || g.to == n);
+            if present {
+                self.check_node(n, params)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether this represents a valid genome with the input and
+    /// output layout given by the parameters. See `check` for details.
+    pub fn validate(&self, params: &Params) -> bool {
+        self.check(params).is_ok()
+    }
+
+    /// Returns whether the connections, including disabled ones, form a
+    /// cycle.
+    pub fn has_cycle(&self) -> bool {
+        self.genes.iter().any(|g| self.creates_cycle_without(g))
+    }
+
+    /// Checks that the genes are in strictly increasing order of innovation
+    /// number.
+    fn check_order(&self) -> Result<(), GenomeError> {
+        let mut last = 0;
+        for gene in &self.genes {
+            if gene.innovation == last {
+                return Err(GenomeError::DuplicateInnovation(last));
+            } else if gene.innovation < last {
+                return Err(GenomeError::NonIncreasingInnovation(
+                    last,
+                    gene.innovation,
+                ));
+            }
+            last = gene.innovation;
+        }
+        Ok(())
+    }
+
+    /// Checks that there is at most one node gene for each node, and none for
+    /// inputs.
+    fn check_node_genes(&self, params: &Params) -> Result<(), GenomeError> {
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if params.is_input(node.id) {
+                return Err(GenomeError::InputNodeGene(node.id));
+            }
+            if !ids.insert(node.id) {
+                return Err(GenomeError::DuplicateNode(node.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the node with the given ID has connections, if it is a
+    /// hidden node.
+    fn check_node(&self, n: usize, params: &Params) -> Result<(), GenomeError> {
+        if n >= params.first_hidden()
+            && !self.genes.iter().any(|g| g.from == n || g.to == n)
+        {
+            Err(GenomeError::DanglingHiddenNode(n))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether there is a path from `gene.to` to `gene.from` that does
+    /// not use `gene` itself.
+    fn creates_cycle_without(&self, gene: &Gene) -> bool {
+        let mut stack = vec![gene.to];
+        let mut visited = HashSet::new();
+        while let Some(n) = stack.pop() {
+            if n == gene.from {
+                return true;
+            }
+            if visited.insert(n) {
+                stack.extend(
+                    self.genes
+                        .iter()
+                        .filter(|g| g.from == n && *g != gene)
+                        .map(|g| g.to),
+                );
+            }
+        }
+        false
+    }
+}
+
+/// Checks the properties of a single gene.
+fn check_gene(gene: &Gene, params: &Params) -> Result<(), GenomeError> {
+    if gene.weight.is_nan() {
+        Err(GenomeError::NanWeight(gene.innovation))
+    } else if params.is_input(gene.to) {
+        Err(GenomeError::EdgeIntoInput(gene.innovation))
+    } else if params.is_output(gene.from) {
+        Err(GenomeError::EdgeOutOfOutput(gene.innovation))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32;
+
+    use fixtures::{gene, params};
+    use genome::{Gene, Genome, NodeGene};
+
+    use super::GenomeError;
+
+    fn check(genes: Vec<Gene>) -> Result<(), GenomeError> {
+        let params = params();
+        let parent = Genome::new(&params);
+        let genome = Genome {
+            nodes: parent.nodes.clone(),
+            genes,
+        };
+        let result = genome.check(&params);
+        assert_eq!(genome.check_since(&parent, &params), result);
+        result
+    }
+
+    #[test]
+    fn check_finds_errors() {
+        assert_eq!(check(vec![gene(1, 5, 1), gene(5, 0, 2)]), Ok(()));
+        assert_eq!(
+            check(vec![gene(1, 0, 1), gene(2, 0, 1)]),
+            Err(GenomeError::DuplicateInnovation(1))
+        );
+        assert_eq!(
+            check(vec![gene(1, 0, 2), gene(2, 0, 1)]),
+            Err(GenomeError::NonIncreasingInnovation(2, 1))
+        );
+        assert_eq!(
+            check(vec![gene(1, 0, 1), gene(1, 0, 2)]),
+            Err(GenomeError::DuplicateConnection(1, 2))
+        );
+        assert_eq!(
+            check(vec![gene(1, 2, 1)]),
+            Err(GenomeError::EdgeIntoInput(1))
+        );
+        assert_eq!(
+            check(vec![gene(0, 5, 1)]),
+            Err(GenomeError::EdgeOutOfOutput(1))
+        );
+        assert_eq!(check(vec![gene(1, 5, 1)]), Ok(()));
+        assert_eq!(check(vec![gene(5, 0, 1)]), Ok(()));
+        assert_eq!(
+            check(vec![
+                gene(1, 5, 1),
+                gene(5, 0, 2),
+                gene(5, 6, 3),
+                gene(6, 5, 4),
+            ]),
+            Err(GenomeError::Cycle(3))
+        );
+
+        let mut nan = gene(1, 0, 1);
+        nan.weight = f32::NAN;
+        assert_eq!(check(vec![nan]), Err(GenomeError::NanWeight(1)));
+    }
+
+    #[test]
+    fn check_finds_bare_node_genes() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.nodes.push(NodeGene::new(5, params.activation));
+        assert_eq!(
+            genome.check(&params),
+            Err(GenomeError::DanglingHiddenNode(5))
+        );
+    }
+
+    #[test]
+    fn check_finds_duplicate_node_genes() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![gene(1, 5, 1), gene(5, 0, 2)];
+        genome.nodes.push(NodeGene::new(5, params.activation));
+        genome.nodes.push(NodeGene::new(5, params.activation));
+        assert_eq!(genome.check(&params), Err(GenomeError::DuplicateNode(5)));
+    }
+
+    #[test]
+    fn check_finds_input_node_genes() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![gene(1, 0, 1)];
+        genome.nodes.push(NodeGene::new(1, params.activation));
+        assert_eq!(genome.check(&params), Err(GenomeError::InputNodeGene(1)));
+    }
+}
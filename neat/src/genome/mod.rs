@@ -1,103 +1,223 @@
+mod check;
 mod mutate;
+mod text;
 
 use std::collections::HashSet;
-use std::fmt::{Display, Formatter, Result as FmtResult};
-use std::iter::FromIterator;
+use std::ops::Index;
 
 use rand::Rng;
 
+use activation::Activation;
+use innovation::InnovationRegistry;
 use params::Params;
 
+pub use self::check::GenomeError;
+pub use self::mutate::Mutation;
+pub use self::text::ParseGenomeError;
+
 /// The entire genome of an organism.
 ///
-/// Note that all genomes have the same nodes 0-4, with 1-4 being inputs and 0
-/// being the output. Other nodes are inferred by being referenced.
-#[derive(Clone, Debug, Deserialize, Index, PartialEq, Serialize)]
+/// Note that all genomes in a population have the same output and input nodes,
+/// as declared by `Params::outputs` and `Params::inputs`. The outputs are nodes
+/// `0..outputs`, and the inputs are the nodes immediately after them. Other
+/// nodes are inferred by being referenced, and have IDs allocated by the
+/// population's `InnovationRegistry`, so a node ID means the same thing in
+/// every genome.
+///
+/// Output and hidden nodes may have a node gene giving their bias and
+/// activation function. A node without one has no bias, and uses the
+/// activation function from the parameters.
+///
+/// # Text format
+///
+/// Genomes are written by `Display` and read by `FromStr` one record per
+/// line. Each record is a keyword followed by fields separated by whitespace:
+///
+/// ```text
+/// node <id> <bias> <activation>
+/// gene <from> <to> <enabled|disabled> <weight> <innovation>
+/// ```
+///
+/// Node genes come first, in order of ID, followed by the connection genes in
+/// the order they appear in the genome. Blank lines and lines starting with
+/// `#` are ignored when parsing. Numbers are written with enough precision to
+/// be read back exactly, so a genome survives a round trip unchanged.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Genome {
+    /// The node genes, ordered by node ID.
+    pub nodes: Vec<NodeGene>,
+
     /// The connection genes.
     pub genes: Vec<Gene>,
 }
 
 impl Genome {
-    /// Creates a new, empty genome.
-    pub fn new() -> Genome {
+    /// Creates a new genome with no connections, and a node gene for each
+    /// output.
+    pub fn new(params: &Params) -> Genome {
+        let activation = params.output_activation.unwrap_or(params.activation);
         Genome {
+            nodes: (0..params.outputs)
+                .map(|id| NodeGene::new(id, activation))
+                .collect(),
             genes: Vec::new(),
         }
     }
 
+    /// Returns the node gene for the node with the given ID, if it has one.
+    pub fn node(&self, id: usize) -> Option<&NodeGene> {
+        self.nodes
+            .binary_search_by_key(&id, |n| n.id)
+            .ok()
+            .map(|i| &self.nodes[i])
+    }
+
+    /// Returns the bias and activation function of the node with the given
+    /// ID, falling back to the defaults from the parameters if it has no node
+    /// gene.
+    pub fn node_or_default(&self, id: usize, params: &Params) -> NodeGene {
+        self.node(id).cloned().unwrap_or_else(|| {
+            let activation = if params.is_output(id) {
+                params.output_activation.unwrap_or(params.activation)
+            } else {
+                params.activation
+            };
+            NodeGene::new(id, activation)
+        })
+    }
+
     /// Returns the number of genes in the genome.
     pub fn len(&self) -> usize {
         self.genes.len()
     }
 
-    /// Performs a random mutation.
-    pub fn mutate<I: FnMut() -> usize, R: Rng>(
+    /// Returns the IDs of the nodes in the genome, in ascending order. This
+    /// always includes the output and input nodes, as well as every node with
+    /// a node gene or a connection.
+    pub fn nodes(&self, params: &Params) -> Vec<usize> {
+        let mut nodes = (0..params.first_hidden())
+            .chain(self.nodes.iter().map(|n| n.id))
+            .chain(self.genes.iter().flat_map(|g| vec![g.from, g.to]))
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Returns whether adding a connection from `from` to `to` would create a
+    /// cycle. Disabled genes are counted, since they may be re-enabled later.
+    pub fn creates_cycle(&self, from: usize, to: usize) -> bool {
+        let mut stack = vec![to];
+        let mut visited = HashSet::new();
+        while let Some(n) = stack.pop() {
+            if n == from {
+                return true;
+            }
+            if visited.insert(n) {
+                stack.extend(
+                    self.genes.iter().filter(|g| g.from == n).map(|g| g.to),
+                );
+            }
+        }
+        false
+    }
+
+    /// Performs random mutations. Each kind of mutation is made with its own
+    /// chance, as given by the parameters. Innovation numbers for structural
+    /// mutations come from the given registry.
+    pub fn mutate<R: Rng>(
         &mut self,
         r: &mut R,
-        inno: I,
+        innovations: &mut InnovationRegistry,
         params: &Params,
     ) {
-        match r.gen_range(0, 3) {
-            0 => self.mutate_add_connection(r, inno),
-            1 => self.mutate_add_node(r, inno),
-            2 => self.mutate_reweight(r, params),
-            _ => unreachable!(),
+        for &mutation in Mutation::ALL {
+            if r.next_f32() < mutation.chance(params) {
+                self.apply_mutation(mutation, r, innovations, params);
+            }
         }
     }
 
-    /// Returns whether this represents a valid genome.
-    ///
-    /// This is a somewhat expensive operation.
-    pub fn validate(&self) -> bool {
-        let froms: HashSet<usize> = self.genes.iter().map(|c| c.from).collect();
-        let tos: HashSet<usize> = self.genes.iter().map(|c| c.to).collect();
-
-        let singleton_from: HashSet<usize> =
-            froms.difference(&tos).cloned().collect();
-        let singleton_to: HashSet<usize> =
-            tos.difference(&froms).cloned().collect();
-
-        let froms: HashSet<usize> = HashSet::from_iter(1..=4);
-        let tos: HashSet<usize> = HashSet::from_iter(Some(0));
-
-        if singleton_from != froms || singleton_to != tos {
-            return false;
+    /// Performs random mutations like `mutate`, but checks the genome with
+    /// `check_since` after each one. If a mutation makes the genome invalid,
+    /// returns the kind of mutation along with the error, leaving the invalid
+    /// genome in place.
+    pub fn mutate_checked<R: Rng>(
+        &mut self,
+        r: &mut R,
+        innovations: &mut InnovationRegistry,
+        params: &Params,
+    ) -> Result<(), (Mutation, GenomeError)> {
+        for &mutation in Mutation::ALL {
+            if r.next_f32() < mutation.chance(params) {
+                let parent = self.clone();
+                self.apply_mutation(mutation, r, innovations, params);
+                self.check_since(&parent, params)
+                    .map_err(|err| (mutation, err))?;
+            }
         }
+        Ok(())
+    }
 
-        let mut last = 0;
-        for gene in &self.genes {
-            if gene.innovation <= last {
-                return false;
+    /// Performs a single mutation of the given kind.
+    pub fn apply_mutation<R: Rng>(
+        &mut self,
+        mutation: Mutation,
+        r: &mut R,
+        innovations: &mut InnovationRegistry,
+        params: &Params,
+    ) {
+        match mutation {
+            Mutation::AddConnection => {
+                self.mutate_add_connection(r, innovations, params)
+            }
+            Mutation::AddNode => self.mutate_add_node(r, innovations, params),
+            Mutation::Reweight => self.mutate_reweight(r, params),
+            Mutation::Gaussian => self.mutate_gaussian(r, params),
+            Mutation::ReplaceWeight => self.mutate_replace_weight(r, params),
+            Mutation::ToggleEnable => self.mutate_toggle_enable(r),
+            Mutation::DeleteConnection => {
+                self.mutate_delete_connection(r, params)
+            }
+            Mutation::DeleteOrphanNode => {
+                self.mutate_delete_orphan_node(r, params)
             }
-            last = gene.innovation;
+            Mutation::Bias => self.mutate_bias(r, params),
+            Mutation::Activation => self.mutate_activation(r, params),
         }
-        true
     }
 }
 
-impl Display for Genome {
-    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        write!(fmt, "[")?;
+impl Index<usize> for Genome {
+    type Output = Gene;
 
-        let mut first = true;
-        for gene in &self.genes {
-            if first {
-                first = false;
-            } else {
-                write!(
-                    fmt,
-                    "; {},{},{},{},{}",
-                    gene.from,
-                    gene.to,
-                    gene.enabled,
-                    gene.weight,
-                    gene.innovation
-                )?;
-            }
-        }
+    fn index(&self, n: usize) -> &Gene {
+        &self.genes[n]
+    }
+}
 
-        write!(fmt, "]")
+/// A gene that represents an output or hidden node in the neural net.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NodeGene {
+    /// The ID of the node.
+    pub id: usize,
+
+    /// The bias added to the node's input.
+    pub bias: f32,
+
+    /// The node's activation function.
+    #[serde(with = "::util::tofromstr")]
+    pub activation: Activation,
+}
+
+impl NodeGene {
+    /// Creates a node gene with no bias.
+    pub fn new(id: usize, activation: Activation) -> NodeGene {
+        NodeGene {
+            id,
+            bias: 0.0,
+            activation,
+        }
     }
 }
 
@@ -1,57 +1,131 @@
-use std::cmp::max;
-
 use rand::Rng;
+use rand::distributions::{IndependentSample, Normal};
 
-use genome::{Gene, Genome};
+use activation::Activation;
+use genome::{Gene, Genome, NodeGene};
+use innovation::InnovationRegistry;
 use params::Params;
 
+/// A kind of mutation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mutation {
+    /// See `Genome::mutate_add_connection`.
+    AddConnection,
+
+    /// See `Genome::mutate_add_node`.
+    AddNode,
+
+    /// See `Genome::mutate_reweight`.
+    Reweight,
+
+    /// See `Genome::mutate_gaussian`.
+    Gaussian,
+
+    /// See `Genome::mutate_replace_weight`.
+    ReplaceWeight,
+
+    /// See `Genome::mutate_toggle_enable`.
+    ToggleEnable,
+
+    /// See `Genome::mutate_delete_connection`.
+    DeleteConnection,
+
+    /// See `Genome::mutate_delete_orphan_node`.
+    DeleteOrphanNode,
+
+    /// See `Genome::mutate_bias`.
+    Bias,
+
+    /// See `Genome::mutate_activation`.
+    Activation,
+}
+
+impl Mutation {
+    /// Every kind of mutation, in the order they are tried by
+    /// `Genome::mutate`.
+    pub const ALL: &'static [Mutation] = &[
+        Mutation::AddConnection,
+        Mutation::AddNode,
+        Mutation::Reweight,
+        Mutation::Gaussian,
+        Mutation::ReplaceWeight,
+        Mutation::ToggleEnable,
+        Mutation::DeleteConnection,
+        Mutation::DeleteOrphanNode,
+        Mutation::Bias,
+        Mutation::Activation,
+    ];
+
+    /// Returns the chance of the mutation being made when an individual is
+    /// mutated.
+    pub fn chance(self, params: &Params) -> f32 {
+        match self {
+            Mutation::AddConnection => params.mutate_add_connection,
+            Mutation::AddNode => params.mutate_add_node,
+            Mutation::Reweight => params.mutate_reweight,
+            Mutation::Gaussian => params.mutate_gaussian,
+            Mutation::ReplaceWeight => params.mutate_replace_weight,
+            Mutation::ToggleEnable => params.mutate_toggle_enable,
+            Mutation::DeleteConnection => params.mutate_delete_connection,
+            Mutation::DeleteOrphanNode => params.mutate_delete_orphan_node,
+            Mutation::Bias => params.mutate_bias,
+            Mutation::Activation => params.mutate_activation,
+        }
+    }
+}
+
 impl Genome {
-    /// Adds a random connection.
-    pub fn mutate_add_connection<I: FnMut() -> usize, R: Rng>(
+    /// Adds a random connection between two of the genome's nodes. The
+    /// connection never originates from an output or leads into an input, and
+    /// unless `params.recurrent` is set, never creates a cycle. If every such
+    /// connection already exists, this is a no-op.
+    pub fn mutate_add_connection<R: Rng>(
         &mut self,
         r: &mut R,
-        mut inno: I,
+        innovations: &mut InnovationRegistry,
+        params: &Params,
     ) {
-        let max_neuron = max(
-            5,
-            self.genes
-                .iter()
-                .map(|c| c.to)
-                .max()
-                .unwrap_or(0),
-        );
-
-        let gene = loop {
-            let from = r.gen_range(0, max_neuron);
-            let to = r.gen_range(0, max_neuron);
-            if from == to {
-                continue;
-            }
-
-            if self.genes.iter().any(|g| {
-                (g.from == from && g.to == to) || (g.from == to && g.to == from)
-            }) {
-                continue;
-            }
-
-            break Gene {
+        let pairs = self.feasible_connections(params);
+        if let Some(&(from, to)) = r.choose(&pairs) {
+            let gene = Gene {
                 from,
                 to,
                 enabled: true,
                 weight: r.gen(),
-                innovation: inno(),
+                innovation: innovations.connection(from, to),
             };
-        };
+            self.insert_gene(gene);
+        }
+    }
 
-        self.genes.push(gene);
+    /// Returns every connection that could be added to the genome without
+    /// making it invalid, as (from, to) pairs.
+    pub fn feasible_connections(&self, params: &Params) -> Vec<(usize, usize)> {
+        let nodes = self.nodes(params);
+        let mut pairs = Vec::new();
+        for &from in nodes.iter().filter(|&&n| !params.is_output(n)) {
+            for &to in nodes.iter().filter(|&&n| !params.is_input(n)) {
+                if self.genes.iter().any(|g| g.from == from && g.to == to) {
+                    continue;
+                }
+                if !params.recurrent && self.creates_cycle(from, to) {
+                    continue;
+                }
+                pairs.push((from, to));
+            }
+        }
+        pairs
     }
 
-    /// Splits a connection to add a node. If there are no connections, this
-    /// is a no-op.
-    pub fn mutate_add_node<I: FnMut() -> usize, R: Rng>(
+    /// Splits a connection to add a node, whose ID comes from the registry.
+    /// If there are no connections, or the genome already has the node the
+    /// registry assigns to the chosen split, this is a no-op. In CPPN mode,
+    /// the new node gets a random activation function.
+    pub fn mutate_add_node<R: Rng>(
         &mut self,
         r: &mut R,
-        mut inno: I,
+        innovations: &mut InnovationRegistry,
+        params: &Params,
     ) {
         // Return if there are no connections.
         if self.genes
@@ -63,16 +137,6 @@ impl Genome {
             return;
         }
 
-        // The number of neurons.
-        let max_neuron = max(
-            5,
-            self.genes
-                .iter()
-                .map(|c| c.to)
-                .max()
-                .unwrap_or(0),
-        );
-
         // The edge to split.
         let i = loop {
             let i = r.gen_range(0, self.genes.len());
@@ -83,24 +147,81 @@ impl Genome {
             break i;
         };
 
+        // Return if this edge was split before, and the split was inherited.
+        // The registry is only consulted, so that this does not allocate a
+        // node ID that is never used.
+        let split = self.genes[i].innovation;
+        if let Some(node) = innovations.find_node(split) {
+            if self.genes
+                .iter()
+                .any(|g| g.from == node.node || g.to == node.node)
+            {
+                return;
+            }
+        }
+        let node = innovations.node(split);
+
         // Split the edge.
         self.genes[i].enabled = false;
         let g1 = Gene {
             from: self.genes[i].from,
-            to: max_neuron,
+            to: node.node,
             enabled: true,
             weight: 1.0,
-            innovation: inno(),
+            innovation: node.in_innovation,
         };
         let g2 = Gene {
-            from: max_neuron,
+            from: node.node,
             to: self.genes[i].to,
             enabled: true,
             weight: self.genes[i].weight,
-            innovation: inno(),
+            innovation: node.out_innovation,
         };
-        self.genes.push(g1);
-        self.genes.push(g2);
+        self.insert_gene(g1);
+        self.insert_gene(g2);
+
+        let i = self.nodes
+            .iter()
+            .position(|n| n.id > node.node)
+            .unwrap_or(self.nodes.len());
+        let activation = if params.cppn {
+            *r.choose(Activation::palette(params)).unwrap()
+        } else {
+            params.activation
+        };
+        self.nodes.insert(i, NodeGene::new(node.node, activation));
+    }
+
+    /// Inserts a gene, keeping the genes ordered by innovation number. Since
+    /// innovation numbers are shared between identical mutations, a new gene
+    /// may have an older innovation number than the genome's newest gene.
+    fn insert_gene(&mut self, gene: Gene) {
+        let i = self.genes
+            .iter()
+            .position(|g| g.innovation > gene.innovation)
+            .unwrap_or(self.genes.len());
+        self.genes.insert(i, gene);
+    }
+
+    /// Modifies node biases, in the same way as `mutate_reweight` modifies
+    /// connection weights.
+    pub fn mutate_bias<R: Rng>(&mut self, r: &mut R, params: &Params) {
+        for node in self.nodes.iter_mut() {
+            if r.next_f32() < params.reweight_rate {
+                node.bias += r.gen_range(
+                    -params.reweight_amount,
+                    params.reweight_amount,
+                );
+            }
+        }
+    }
+
+    /// Changes the activation function of a random node to one from
+    /// `Activation::palette`. If there are no node genes, this is a no-op.
+    pub fn mutate_activation<R: Rng>(&mut self, r: &mut R, params: &Params) {
+        if let Some(node) = r.choose_mut(&mut self.nodes) {
+            node.activation = *r.choose(Activation::palette(params)).unwrap();
+        }
     }
 
     /// Modifies connection weights.
@@ -114,4 +235,314 @@ impl Genome {
             }
         }
     }
+
+    /// Modifies connection weights by adding Gaussian noise.
+    pub fn mutate_gaussian<R: Rng>(&mut self, r: &mut R, params: &Params) {
+        let normal = Normal::new(0.0, params.gaussian_stddev as f64);
+        for gene in self.genes.iter_mut() {
+            if r.next_f32() < params.reweight_rate {
+                gene.weight += normal.ind_sample(r) as f32;
+            }
+        }
+    }
+
+    /// Replaces the weight of a random connection with a new random weight.
+    /// If there are no connections, this is a no-op.
+    pub fn mutate_replace_weight<R: Rng>(
+        &mut self,
+        r: &mut R,
+        params: &Params,
+    ) {
+        let weight = r.gen_range(-params.weight_range, params.weight_range);
+        if let Some(gene) = r.choose_mut(&mut self.genes) {
+            gene.weight = weight;
+        }
+    }
+
+    /// Enables a random disabled connection, or disables a random enabled one.
+    /// If there are no connections, this is a no-op.
+    pub fn mutate_toggle_enable<R: Rng>(&mut self, r: &mut R) {
+        if let Some(gene) = r.choose_mut(&mut self.genes) {
+            gene.enabled = !gene.enabled;
+        }
+    }
+
+    /// Removes a random connection. This may leave orphans for
+    /// `mutate_delete_orphan_node` to remove, but a hidden node left without
+    /// any connections loses its node gene as well. If there are no
+    /// connections, this is a no-op.
+    pub fn mutate_delete_connection<R: Rng>(
+        &mut self,
+        r: &mut R,
+        params: &Params,
+    ) {
+        if self.genes.is_empty() {
+            return;
+        }
+
+        let gene = self.genes.remove(r.gen_range(0, self.genes.len()));
+        for &n in &[gene.from, gene.to] {
+            if n >= params.first_hidden()
+                && !self.genes.iter().any(|g| g.from == n || g.to == n)
+            {
+                self.nodes.retain(|node| node.id != n);
+            }
+        }
+    }
+
+    /// Removes a random orphan, along with its node gene and connections. Any
+    /// hidden nodes this turns into orphans are removed in the same way. If
+    /// there are no orphans, this is a no-op.
+    pub fn mutate_delete_orphan_node<R: Rng>(
+        &mut self,
+        r: &mut R,
+        params: &Params,
+    ) {
+        let orphans = self.nodes(params)
+            .into_iter()
+            .filter(|&n| self.is_orphan(n, params))
+            .collect::<Vec<_>>();
+
+        let mut removed = match r.choose(&orphans) {
+            Some(&n) => vec![n],
+            None => return,
+        };
+        while let Some(n) = removed.pop() {
+            let neighbours = self.genes
+                .iter()
+                .filter(|g| g.from == n || g.to == n)
+                .map(|g| if g.from == n { g.to } else { g.from })
+                .collect::<Vec<_>>();
+            self.genes.retain(|g| g.from != n && g.to != n);
+            self.nodes.retain(|node| node.id != n);
+
+            for m in neighbours {
+                let orphaned = m != n
+                    && !removed.contains(&m)
+                    && self.is_orphan(m, params);
+                if orphaned {
+                    removed.push(m);
+                }
+            }
+        }
+    }
+
+    /// Returns whether the node with the given ID is an orphan, which is a
+    /// hidden node with no enabled incoming or no enabled outgoing
+    /// connections.
+    pub fn is_orphan(&self, n: usize, params: &Params) -> bool {
+        n >= params.first_hidden()
+            && (!self.genes.iter().any(|g| g.enabled && g.to == n)
+                || !self.genes.iter().any(|g| g.enabled && g.from == n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    use fixtures::{gene, params, weighted};
+    use genome::{Genome, NodeGene};
+    use innovation::InnovationRegistry;
+
+    use super::Mutation;
+
+    #[test]
+    fn add_node_ids_come_from_registry() {
+        let params = params();
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut innovations = InnovationRegistry::new(params.first_hidden());
+        let first = innovations.connection(1, 0);
+        let second = innovations.connection(2, 0);
+
+        // Identical splits in different genomes get the same node.
+        let mut genome = Genome::new(&params);
+        genome.genes.push(gene(1, 0, first));
+        let mut other = genome.clone();
+        other.genes.push(gene(2, 0, second));
+        other.genes[1].enabled = false;
+        genome.mutate_add_node(&mut rng, &mut innovations, &params);
+        other.mutate_add_node(&mut rng, &mut innovations, &params);
+        assert_eq!(genome.nodes(&params), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(other.nodes(&params), genome.nodes(&params));
+        assert_eq!(genome.genes[1..], other.genes[2..]);
+
+        // Splitting an edge whose split was inherited is a no-op, and does
+        // not use up a node ID.
+        genome.genes[0].enabled = true;
+        genome.genes[1].enabled = false;
+        genome.genes[2].enabled = false;
+        let split = genome.clone();
+        let last = innovations.last();
+        genome.mutate_add_node(&mut rng, &mut innovations, &params);
+        assert_eq!(genome, split);
+        assert_eq!(innovations.last(), last);
+
+        let mut genome = Genome::new(&params);
+        genome.genes.push(gene(2, 0, second));
+        genome.mutate_add_node(&mut rng, &mut innovations, &params);
+        assert_eq!(genome.nodes(&params), vec![0, 1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn weight_mutations() {
+        let mut params = params();
+        params.reweight_rate = 1.0;
+        params.weight_range = 0.5;
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![weighted(1, 0, 4.0, 1), weighted(2, 0, 4.0, 2)];
+        let mut rng = XorShiftRng::new_unseeded();
+
+        params.gaussian_stddev = 0.0;
+        let unchanged = genome.clone();
+        genome.mutate_gaussian(&mut rng, &params);
+        assert_eq!(genome, unchanged);
+
+        params.gaussian_stddev = 1.0;
+        genome.mutate_gaussian(&mut rng, &params);
+        assert!(genome.genes.iter().all(|g| g.weight != 4.0));
+
+        genome.genes = vec![weighted(1, 0, 4.0, 1), weighted(2, 0, 4.0, 2)];
+        genome.mutate_replace_weight(&mut rng, &params);
+        let replaced = genome
+            .genes
+            .iter()
+            .filter(|g| g.weight != 4.0)
+            .collect::<Vec<_>>();
+        assert_eq!(replaced.len(), 1);
+        assert!(replaced[0].weight.abs() < 0.5);
+    }
+
+    #[test]
+    fn toggle_enable_flips_one_gene() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![gene(1, 0, 1), gene(2, 0, 2), gene(3, 0, 3)];
+        genome.genes[1].enabled = false;
+        let parent = genome.clone();
+
+        let mut rng = XorShiftRng::new_unseeded();
+        genome.mutate_toggle_enable(&mut rng);
+        let flipped = genome
+            .genes
+            .iter()
+            .zip(&parent.genes)
+            .filter(|&(g, p)| g != p)
+            .collect::<Vec<_>>();
+        assert_eq!(flipped.len(), 1);
+        let (g, p) = flipped[0];
+        assert_eq!(g.enabled, !p.enabled);
+        assert_eq!(g.innovation, p.innovation);
+    }
+
+    #[test]
+    fn delete_connection_removes_one_gene() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        genome.genes = vec![gene(1, 5, 1), gene(5, 0, 2), gene(2, 0, 3)];
+        genome.nodes.push(NodeGene::new(5, params.activation));
+
+        let mut rng = XorShiftRng::new_unseeded();
+        for len in (0..3).rev() {
+            let parent = genome.clone();
+            genome.mutate_delete_connection(&mut rng, &params);
+            assert_eq!(genome.genes.len(), len);
+            assert!(genome.genes.iter().all(|g| parent.genes.contains(g)));
+
+            // The hidden node keeps its node gene while it has connections.
+            let connected =
+                genome.genes.iter().any(|g| g.from == 5 || g.to == 5);
+            assert_eq!(genome.node(5).is_some(), connected);
+        }
+        assert_eq!(genome, Genome::new(&params));
+    }
+
+    #[test]
+    fn delete_orphan_node_cascades() {
+        let params = params();
+        let mut genome = Genome::new(&params);
+        let mut disabled = gene(1, 5, 1);
+        disabled.enabled = false;
+        genome.genes = vec![
+            disabled,
+            gene(5, 6, 2),
+            gene(6, 0, 3),
+            gene(2, 7, 4),
+            gene(7, 0, 5),
+        ];
+        for &n in &[5, 6, 7] {
+            genome.nodes.push(NodeGene::new(n, params.activation));
+        }
+        assert!(genome.is_orphan(5, &params));
+        assert!(!genome.is_orphan(6, &params));
+
+        // Removing node 5 leaves node 6 without incoming connections.
+        let mut rng = XorShiftRng::new_unseeded();
+        genome.mutate_delete_orphan_node(&mut rng, &params);
+        assert_eq!(
+            genome.genes.iter().map(|g| g.innovation).collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+        assert_eq!(
+            genome.nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+            vec![0, 7]
+        );
+
+        let orphanless = genome.clone();
+        genome.mutate_delete_orphan_node(&mut rng, &params);
+        assert_eq!(genome, orphanless);
+    }
+
+    #[test]
+    fn mutations_stay_valid() {
+        for seed in 1..50 {
+            let mut rng = XorShiftRng::from_seed([seed, 2, 3, 4]);
+            let mut params = params();
+            params.recurrent = seed % 2 == 0;
+            let mut innovations =
+                InnovationRegistry::new(params.first_hidden());
+            let mut genome = Genome::new(&params);
+            for _ in 0..200 {
+                let mutation = *rng.choose(Mutation::ALL).unwrap();
+                genome.apply_mutation(
+                    mutation,
+                    &mut rng,
+                    &mut innovations,
+                    &params,
+                );
+                if let Err(err) = genome.check(&params) {
+                    panic!("{:?} made an invalid genome: {}", mutation, err);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn add_connection_saturates() {
+        for &recurrent in &[false, true] {
+            let mut rng = XorShiftRng::new_unseeded();
+            let mut params = params();
+            params.recurrent = recurrent;
+            let mut innovations =
+                InnovationRegistry::new(params.first_hidden());
+            let mut genome = Genome::new(&params);
+            genome.mutate_add_connection(&mut rng, &mut innovations, &params);
+            genome.mutate_add_node(&mut rng, &mut innovations, &params);
+
+            let feasible = genome.feasible_connections(&params).len();
+            for _ in 0..feasible {
+                genome.mutate_add_connection(
+                    &mut rng,
+                    &mut innovations,
+                    &params,
+                );
+            }
+            assert!(genome.feasible_connections(&params).is_empty());
+
+            let saturated = genome.clone();
+            genome.mutate_add_connection(&mut rng, &mut innovations, &params);
+            assert_eq!(genome, saturated);
+            assert!(genome.validate(&params));
+        }
+    }
 }
@@ -0,0 +1,204 @@
+//! The text format for genomes, described on `Genome`.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use genome::{Gene, Genome, NodeGene};
+
+impl Display for Genome {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        for node in &self.nodes {
+            writeln!(
+                fmt,
+                "node {} {} {}",
+                node.id, node.bias, node.activation
+            )?;
+        }
+        for gene in &self.genes {
+            writeln!(
+                fmt,
+                "gene {} {} {} {} {}",
+                gene.from,
+                gene.to,
+                if gene.enabled { "enabled" } else { "disabled" },
+                gene.weight,
+                gene.innovation
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Genome {
+    type Err = ParseGenomeError;
+
+    fn from_str(s: &str) -> Result<Genome, ParseGenomeError> {
+        let mut genome = Genome {
+            nodes: Vec::new(),
+            genes: Vec::new(),
+        };
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match fields[0] {
+                "node" if fields.len() == 4 => {
+                    let id = parse_field(fields[1], line_no, "id")?;
+                    if genome.nodes.iter().any(|n| n.id == id) {
+                        return Err(ParseGenomeError::DuplicateNode(line_no));
+                    }
+                    genome.nodes.push(NodeGene {
+                        id,
+                        bias: parse_field(fields[2], line_no, "bias")?,
+                        activation: parse_field(
+                            fields[3],
+                            line_no,
+                            "activation",
+                        )?,
+                    })
+                }
+                "gene" if fields.len() == 6 => genome.genes.push(Gene {
+                    from: parse_field(fields[1], line_no, "from")?,
+                    to: parse_field(fields[2], line_no, "to")?,
+                    enabled: match fields[3] {
+                        "enabled" => true,
+                        "disabled" => false,
+                        _ => {
+                            return Err(ParseGenomeError::InvalidField(
+                                line_no,
+                                "enabled",
+                            ))
+                        }
+                    },
+                    weight: parse_field(fields[4], line_no, "weight")?,
+                    innovation: parse_field(fields[5], line_no, "innovation")?,
+                }),
+                "node" | "gene" => {
+                    return Err(ParseGenomeError::WrongFieldCount(line_no))
+                }
+                _ => return Err(ParseGenomeError::UnknownRecord(line_no)),
+            }
+        }
+
+        genome.nodes.sort_by_key(|n| n.id);
+        Ok(genome)
+    }
+}
+
+/// Parses a single field of a record.
+fn parse_field<T: FromStr>(
+    field: &str,
+    line_no: usize,
+    name: &'static str,
+) -> Result<T, ParseGenomeError> {
+    field
+        .parse()
+        .map_err(|_| ParseGenomeError::InvalidField(line_no, name))
+}
+
+/// An error parsing a genome from text. Each variant carries the line number,
+/// starting from 1, that the error occurred on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseGenomeError {
+    /// The line does not start with `node` or `gene`.
+    UnknownRecord(usize),
+
+    /// The record has too many or too few fields.
+    WrongFieldCount(usize),
+
+    /// The field with the given name could not be parsed.
+    InvalidField(usize, &'static str),
+
+    /// An earlier `node` record has the same ID.
+    DuplicateNode(usize),
+}
+
+impl Display for ParseGenomeError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            ParseGenomeError::UnknownRecord(line) => {
+                write!(fmt, "line {}: unknown record", line)
+            }
+            ParseGenomeError::WrongFieldCount(line) => {
+                write!(fmt, "line {}: wrong number of fields", line)
+            }
+            ParseGenomeError::InvalidField(line, name) => {
+                write!(fmt, "line {}: invalid {}", line, name)
+            }
+            ParseGenomeError::DuplicateNode(line) => {
+                write!(fmt, "line {}: duplicate node", line)
+            }
+        }
+    }
+}
+
+impl Error for ParseGenomeError {
+    fn description(&self) -> &str {
+        "invalid genome text"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::XorShiftRng;
+
+    use activation::Activation;
+    use fixtures::params;
+    use genome::{Genome, NodeGene};
+    use innovation::InnovationRegistry;
+
+    use super::ParseGenomeError;
+
+    #[test]
+    fn round_trip() {
+        let params = params();
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut innovations = InnovationRegistry::new(params.first_hidden());
+        let mut genome = Genome::new(&params);
+        for _ in 0..50 {
+            genome.mutate(&mut rng, &mut innovations, &params);
+        }
+        genome.nodes.push(NodeGene {
+            id: 100,
+            bias: 0.1 + 0.2,
+            activation: Activation::Tanh,
+        });
+        assert!(genome.genes.len() > 1);
+
+        let text = genome.to_string();
+        assert_eq!(text.lines().count(), genome.nodes.len() + genome.len());
+        assert_eq!(text.parse::<Genome>(), Ok(genome));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |s: &str| s.parse::<Genome>().map(|_| ());
+        assert_eq!(parse("# comment\n\ngene 1 0 enabled 0.5 1\n"), Ok(()));
+        assert_eq!(
+            parse("node 0 0"),
+            Err(ParseGenomeError::WrongFieldCount(1))
+        );
+        assert_eq!(
+            parse("\nedge 1 0 enabled 0.5 1"),
+            Err(ParseGenomeError::UnknownRecord(2))
+        );
+        assert_eq!(
+            parse("gene 1 0 on 0.5 1"),
+            Err(ParseGenomeError::InvalidField(1, "enabled"))
+        );
+        assert_eq!(
+            parse("node 0 0 Linear"),
+            Err(ParseGenomeError::InvalidField(1, "activation"))
+        );
+        assert_eq!(
+            parse("node 5 0 Tanh\nnode 0 0 Tanh\nnode 5 1 ReLU"),
+            Err(ParseGenomeError::DuplicateNode(3))
+        );
+    }
+}
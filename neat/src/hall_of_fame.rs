@@ -0,0 +1,117 @@
+use std::cmp::Reverse;
+
+use float_ord::FloatOrd;
+
+use genome::Genome;
+
+/// The fittest genomes ever evaluated, so that the best found is never lost.
+///
+/// Each genome appears at most once. If a genome is evaluated again, as an
+/// elite is, its entry keeps the best fitness it was given.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HallOfFame {
+    entries: Vec<HallOfFameEntry>,
+}
+
+/// A genome in the hall of fame.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HallOfFameEntry {
+    /// The genome.
+    pub genome: Genome,
+
+    /// The best fitness the genome was given.
+    pub fitness: f32,
+
+    /// The generation the genome was first evaluated in.
+    pub generation: usize,
+}
+
+impl HallOfFame {
+    /// Creates a new, empty hall of fame.
+    pub fn new() -> HallOfFame {
+        HallOfFame::default()
+    }
+
+    /// Returns the number of genomes in the hall of fame.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the hall of fame is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries in the hall of fame, fittest first. Entries with
+    /// the same fitness are in the order they were added.
+    pub fn entries(&self) -> &[HallOfFameEntry] {
+        &self.entries
+    }
+
+    /// Records that a genome was evaluated in the given generation. It is
+    /// added to the hall of fame if it is among the `capacity` fittest
+    /// genomes seen, and the least fit entries are dropped to keep at most
+    /// `capacity` of them.
+    pub fn insert(
+        &mut self,
+        genome: &Genome,
+        fitness: f32,
+        generation: usize,
+        capacity: usize,
+    ) {
+        let existing = self.entries.iter().position(|e| e.genome == *genome);
+        if let Some(i) = existing {
+            let entry = &mut self.entries[i];
+            entry.fitness = entry.fitness.max(fitness);
+        } else if self.entries.len() < capacity
+            || self.entries.iter().any(|e| fitness > e.fitness)
+        {
+            self.entries.push(HallOfFameEntry {
+                genome: genome.clone(),
+                fitness,
+                generation,
+            });
+        }
+
+        self.entries.sort_by_key(|e| Reverse(FloatOrd(e.fitness)));
+        self.entries.truncate(capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fixtures::params;
+    use genome::Genome;
+
+    use super::HallOfFame;
+
+    #[test]
+    fn keeps_fittest() {
+        let params = params();
+        let genomes = (0..4)
+            .map(|i| {
+                let mut genome = Genome::new(&params);
+                genome.nodes[0].bias = i as f32;
+                genome
+            })
+            .collect::<Vec<_>>();
+
+        let mut hall = HallOfFame::new();
+        hall.insert(&genomes[0], 1.0, 0, 2);
+        hall.insert(&genomes[1], 3.0, 0, 2);
+        hall.insert(&genomes[2], 2.0, 1, 2);
+        hall.insert(&genomes[3], 0.5, 1, 2);
+        let fitnesses = hall.entries().iter().map(|e| e.fitness);
+        assert_eq!(fitnesses.collect::<Vec<_>>(), vec![3.0, 2.0]);
+
+        hall.insert(&genomes[2], 4.0, 2, 2);
+        hall.insert(&genomes[2], 1.0, 3, 2);
+        assert_eq!(hall.len(), 2);
+        assert_eq!(hall.entries()[0].genome, genomes[2]);
+        assert_eq!(hall.entries()[0].fitness, 4.0);
+        assert_eq!(hall.entries()[0].generation, 1);
+
+        hall.insert(&genomes[3], 5.0, 4, 0);
+        assert!(hall.is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+/// A record of the structural mutations made in a population, which assigns
+/// the same innovation numbers and node IDs to identical mutations.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InnovationRegistry {
+    last: usize,
+    last_node: usize,
+    connections: BTreeMap<(usize, usize), usize>,
+    nodes: BTreeMap<usize, NodeInnovation>,
+}
+
+/// The node ID and innovation numbers assigned to an add-node mutation.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NodeInnovation {
+    /// The ID of the new node.
+    pub node: usize,
+
+    /// The innovation number of the connection into the new node.
+    pub in_innovation: usize,
+
+    /// The innovation number of the connection out of the new node.
+    pub out_innovation: usize,
+}
+
+impl InnovationRegistry {
+    /// Creates a new, empty registry, which gives hidden nodes IDs starting
+    /// from `first_hidden`.
+    pub fn new(first_hidden: usize) -> InnovationRegistry {
+        InnovationRegistry {
+            last: 0,
+            last_node: first_hidden - 1,
+            connections: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the most recently allocated innovation number, or zero if none
+    /// have been allocated.
+    pub fn last(&self) -> usize {
+        self.last
+    }
+
+    /// Returns the number of distinct add-connection and add-node mutations
+    /// recorded since the registry was created or last cleared.
+    pub fn len(&self) -> usize {
+        self.connections.len() + self.nodes.len()
+    }
+
+    /// Returns whether no mutations have been recorded since the registry was
+    /// created or last cleared.
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty() && self.nodes.is_empty()
+    }
+
+    /// Returns the innovation number for a connection from `from` to `to`.
+    pub fn connection(&mut self, from: usize, to: usize) -> usize {
+        let last = &mut self.last;
+        *self.connections
+            .entry((from, to))
+            .or_insert_with(|| next(last))
+    }
+
+    /// Returns the node ID and innovation numbers for the node created when the
+    /// gene with the given innovation number is split.
+    pub fn node(&mut self, split: usize) -> NodeInnovation {
+        let last = &mut self.last;
+        let last_node = &mut self.last_node;
+        *self.nodes
+            .entry(split)
+            .or_insert_with(|| NodeInnovation {
+                node: next(last_node),
+                in_innovation: next(last),
+                out_innovation: next(last),
+            })
+    }
+
+    /// Returns the node ID and innovation numbers already assigned to splitting
+    /// the gene with the given innovation number, without assigning them if
+    /// they have not been.
+    pub fn find_node(&self, split: usize) -> Option<NodeInnovation> {
+        self.nodes.get(&split).cloned()
+    }
+
+    /// Forgets all recorded mutations, so that future mutations get fresh
+    /// innovation numbers and node IDs. Neither is ever reused.
+    pub fn clear(&mut self) {
+        self.connections.clear();
+        self.nodes.clear();
+    }
+}
+
+fn next(last: &mut usize) -> usize {
+    *last += 1;
+    *last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InnovationRegistry, NodeInnovation};
+
+    #[test]
+    fn identical_mutations_share_innovations() {
+        let mut registry = InnovationRegistry::new(5);
+        assert_eq!(registry.connection(1, 0), 1);
+        assert_eq!(registry.connection(2, 0), 2);
+        assert_eq!(registry.connection(1, 0), 1);
+        let node = NodeInnovation {
+            node: 5,
+            in_innovation: 3,
+            out_innovation: 4,
+        };
+        assert_eq!(registry.node(1), node);
+        assert_eq!(registry.node(1), node);
+        assert_eq!(registry.node(2).node, 6);
+        assert_eq!(registry.find_node(1), Some(node));
+        assert_eq!(registry.find_node(3), None);
+        assert_eq!(registry.len(), 4);
+
+        registry.clear();
+        assert!(registry.is_empty());
+        assert_eq!(registry.connection(1, 0), 7);
+        assert_eq!(registry.node(1).node, 7);
+        assert_eq!(registry.last(), 9);
+    }
+}
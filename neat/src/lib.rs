@@ -2,6 +2,9 @@
 //! in Evolving Neural Networks through Augmenting Topologies by Stanley and
 //! Miikkulainen.
 
+#[cfg(test)]
+extern crate bincode;
+extern crate crossbeam;
 #[macro_use]
 extern crate derive_more;
 extern crate float_ord;
@@ -20,15 +23,32 @@ extern crate strum_macros;
 
 mod activation;
 mod crossover;
+mod dot;
+#[cfg(test)]
+mod fixtures;
 mod genome;
+mod hall_of_fame;
+mod innovation;
 mod network;
+mod novelty;
+mod pareto;
 mod params;
 mod population;
 mod species;
+mod substrate;
 mod util;
 
 pub use activation::Activation;
-pub use genome::{Gene, Genome};
+pub use genome::{
+    Gene, Genome, GenomeError, Mutation, NodeGene, ParseGenomeError,
+};
+pub use hall_of_fame::{HallOfFame, HallOfFameEntry};
+pub use innovation::InnovationRegistry;
 pub use network::Network;
-pub use params::Params;
-pub use population::{Population, PopulationIter};
+pub use novelty::NoveltyArchive;
+pub use params::{Params, ParamsError};
+pub use population::{
+    Evaluation, GenerationError, GenerationStats, Population, PopulationIter,
+    SpeciesStats,
+};
+pub use substrate::{Substrate, SubstrateError};
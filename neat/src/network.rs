@@ -1,38 +1,89 @@
 use std::cmp::max;
 
 use activation::Activation;
+use dot::{write_edge, write_header, write_hidden};
 use genome::Genome;
+use params::Params;
 
-/// The representation of a neural network.
+/// The representation of a neural network, compiled to a flat list of steps
+/// that are evaluated in order.
+///
+/// A recurrent network remembers the value of every neuron between calls to
+/// `calculate`, and a connection that closes a cycle carries the value its
+/// source neuron had on the previous call. A feed-forward network forgets
+/// everything between calls.
 #[derive(Debug)]
 pub struct Network {
+    inputs: usize,
+    outputs: usize,
+    neurons: usize,
+    recurrent: bool,
+
+    /// The neurons to calculate, in evaluation order.
+    steps: Vec<Step>,
+
+    /// The index into `values` each connection reads from, grouped by step.
+    sources: Vec<usize>,
+
+    /// The weight of each connection, grouped by step.
+    weights: Vec<f32>,
+
+    /// The value of every neuron on this step, followed by the value of every
+    /// neuron on the previous step.
+    values: Vec<f32>,
+}
+
+/// The calculation of a single neuron's value.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    neuron: usize,
+    bias: f32,
     activation: Activation,
-    neurons: Vec<Neuron>,
+
+    /// The range of `Network::sources` and `Network::weights` holding the
+    /// neuron's incoming connections.
+    start: usize,
+    end: usize,
 }
 
 impl Genome {
-    /// Builds a network from the genome, using the given activation function.
-    pub fn build_network(&self, activation: Activation) -> Network {
+    /// Builds a network from the genome. Nodes without a node gene use the
+    /// activation functions from the given parameters.
+    pub fn build_network(&self, params: &Params) -> Network {
         let mut genes = self.genes.clone();
         genes.sort_by_key(|gene| gene.to);
 
         let iter = genes.iter().filter(|gene| gene.enabled);
-        let to_max = iter.clone()
-            .map(|gene| gene.to)
+        let node_max = iter.clone()
+            .map(|gene| max(gene.from, gene.to))
             .max()
             .unwrap_or(0);
 
-        let mut neurons = vec![Neuron::default(); max(to_max + 1, 5)];
+        let mut incoming =
+            vec![Vec::new(); max(node_max + 1, params.first_hidden())];
         for gene in iter {
-            neurons[gene.to]
-                .incoming
-                .push((gene.from, gene.weight));
+            incoming[gene.to].push((gene.from, gene.weight));
         }
 
-        Network {
-            activation,
-            neurons,
+        let mut network = Network {
+            inputs: params.inputs,
+            outputs: params.outputs,
+            neurons: incoming.len(),
+            recurrent: params.recurrent,
+            steps: Vec::new(),
+            sources: Vec::new(),
+            weights: Vec::new(),
+            values: vec![0.0; 2 * incoming.len()],
+        };
+
+        let mut states = vec![State::White; incoming.len()];
+        for state in &mut states[params.outputs..params.first_hidden()] {
+            *state = State::Black;
+        }
+        for n in 0..params.outputs {
+            network.compile(n, self, &incoming, &mut states, params);
         }
+        network
     }
 }
 
@@ -40,64 +91,395 @@ impl Genome {
 enum State {
     White,
     Gray,
-    Black(f32),
+    Black,
 }
 
-impl State {
-    fn to_option(self) -> Option<f32> {
-        match self {
-            State::Black(x) => Some(x),
-            _ => None,
+impl Network {
+    /// Adds the steps needed to calculate the given neuron, searching its
+    /// inputs depth-first. A connection from a neuron that is still being
+    /// searched closes a cycle, so it reads that neuron's previous value.
+    fn compile(
+        &mut self,
+        n: usize,
+        genome: &Genome,
+        incoming: &[Vec<(usize, f32)>],
+        states: &mut [State],
+        params: &Params,
+    ) {
+        match states[n] {
+            State::Black | State::Gray => return,
+            State::White => states[n] = State::Gray,
+        }
+
+        for &(i, _) in &incoming[n] {
+            self.compile(i, genome, incoming, states, params);
+        }
+
+        let start = self.sources.len();
+        for &(i, w) in &incoming[n] {
+            self.sources.push(match states[i] {
+                State::Black => i,
+                _ => self.neurons + i,
+            });
+            self.weights.push(w);
+        }
+
+        let node = genome.node_or_default(n, params);
+        self.steps.push(Step {
+            neuron: n,
+            bias: node.bias,
+            activation: node.activation,
+            start,
+            end: self.sources.len(),
+        });
+        states[n] = State::Black;
+    }
+
+    /// Returns the number of inputs the network takes.
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    /// Returns the number of outputs the network produces.
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// Returns whether the network keeps its state between calls.
+    pub fn is_recurrent(&self) -> bool {
+        self.recurrent
+    }
+
+    /// Forgets the values remembered from previous calls to `calculate`.
+    pub fn reset(&mut self) {
+        for x in self.values.iter_mut() {
+            *x = 0.0;
         }
     }
+
+    /// Calculates the output values of the network for a given input vector.
+    /// Each hidden and output neuron adds its bias to the weighted sum of its
+    /// inputs, then applies its activation function. For a recurrent network,
+    /// this advances the network by one step.
+    ///
+    /// Panics if `ins` does not have exactly `self.inputs()` elements.
+    pub fn calculate(&mut self, ins: &[f32]) -> Vec<f32> {
+        let mut outs = vec![0.0; self.outputs];
+        self.calculate_into(ins, &mut outs);
+        outs
+    }
+
+    /// Calculates the output values of the network for a given input vector,
+    /// writing them to `outs`. This does not allocate.
+    ///
+    /// Panics if `ins` does not have exactly `self.inputs()` elements, or
+    /// `outs` does not have exactly `self.outputs()` elements.
+    pub fn calculate_into(&mut self, ins: &[f32], outs: &mut [f32]) {
+        assert_eq!(ins.len(), self.inputs);
+        assert_eq!(outs.len(), self.outputs);
+
+        self.values[self.outputs..self.outputs + self.inputs]
+            .copy_from_slice(ins);
+        for step in &self.steps {
+            let mut v = step.bias;
+            for k in step.start..step.end {
+                v += self.values[self.sources[k]] * self.weights[k];
+            }
+            self.values[step.neuron] = step.activation.calculate(v);
+        }
+        outs.copy_from_slice(&self.values[..self.outputs]);
+
+        if self.recurrent {
+            for step in &self.steps {
+                self.values[self.neurons + step.neuron] =
+                    self.values[step.neuron];
+            }
+        }
+    }
+
+    /// Renders the network as a Graphviz graph, in the same style as
+    /// `Genome::to_dot`. Only the neurons and connections that are actually
+    /// evaluated are shown, and connections that carry the previous step's
+    /// value are dotted.
+    pub fn to_dot(&self, input_labels: &[&str]) -> String {
+        let mut dot = String::new();
+        write_header(&mut dot, self.inputs, self.outputs, input_labels);
+        for step in &self.steps {
+            if step.neuron >= self.outputs + self.inputs {
+                write_hidden(&mut dot, step.neuron, step.bias, step.activation);
+            }
+        }
+        for step in &self.steps {
+            for k in step.start..step.end {
+                let (from, style) = if self.sources[k] < self.neurons {
+                    (self.sources[k], "solid")
+                } else {
+                    (self.sources[k] - self.neurons, "dotted")
+                };
+                write_edge(&mut dot, from, step.neuron, self.weights[k], style);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
 }
 
-impl Network {
-    /// Calculates the output value of the network for a given input vector.
-    pub fn calculate(&self, ins: [f32; 4]) -> f32 {
-        let mut values = vec![State::White; self.neurons.len()];
-        for (i, &x) in ins.iter().enumerate() {
-            values[i + 1] = State::Black(x);
+#[cfg(test)]
+mod tests {
+    use std::cmp::max;
+
+    use rand::{Rng, XorShiftRng};
+
+    use activation::Activation;
+    use fixtures::{params, weighted};
+    use genome::{Genome, NodeGene};
+    use innovation::InnovationRegistry;
+    use params::Params;
+
+    fn genome() -> Genome {
+        Genome {
+            nodes: vec![],
+            genes: vec![
+                weighted(1, 5, -1.0, 1),
+                weighted(2, 5, 0.5, 2),
+                weighted(5, 0, 2.0, 3),
+                weighted(3, 0, 0.25, 4),
+            ],
         }
+    }
 
-        fn search(
-            n: usize,
-            activation: Activation,
-            neurons: &[Neuron],
-            values: &mut [State],
-        ) {
-            match values[n] {
-                State::Black(_) => {
-                    return;
-                }
-                State::Gray => {
-                    println!("self-edge?");
-                    return;
+    fn output(activation: Activation, output: Option<Activation>) -> f32 {
+        let mut params = params();
+        params.activation = activation;
+        params.output_activation = output;
+        let mut network = genome().build_network(&params);
+        network.calculate(&[0.5, -0.25, 1.0, 0.0])[0]
+    }
+
+    #[test]
+    fn activations_differ() {
+        let relu = output(Activation::ReLU, None);
+        let sigmoid = output(Activation::Sigmoid, None);
+        let tanh = output(Activation::Tanh, None);
+
+        assert_eq!(relu, 0.25);
+        assert!(sigmoid != relu);
+        assert!(tanh != relu);
+        assert!(tanh != sigmoid);
+
+        let hidden = Activation::Tanh.calculate(-0.5 - 0.125);
+        let expected = Activation::Tanh.calculate(2.0 * hidden + 0.25);
+        assert_eq!(tanh, expected);
+    }
+
+    #[test]
+    fn output_activation_overrides() {
+        let hidden = Activation::Sigmoid.calculate(-0.5 - 0.125);
+        let expected = Activation::ReLU.calculate(2.0 * hidden + 0.25);
+        let actual = output(Activation::Sigmoid, Some(Activation::ReLU));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn multiple_outputs() {
+        let mut params = params();
+        params.activation = Activation::ReLU;
+        params.inputs = 2;
+        params.outputs = 3;
+
+        let genome = Genome {
+            nodes: vec![],
+            genes: vec![
+                weighted(3, 0, 1.0, 1),
+                weighted(4, 1, 2.0, 2),
+                weighted(3, 5, 1.0, 3),
+                weighted(4, 5, 1.0, 4),
+                weighted(5, 2, 1.0, 5),
+            ],
+        };
+
+        let mut network = genome.build_network(&params);
+        assert_eq!(network.calculate(&[1.0, 2.0]), vec![1.0, 4.0, 3.0]);
+    }
+
+    #[test]
+    fn recurrent_memory() {
+        let mut params = params();
+        params.activation = Activation::ReLU;
+        params.recurrent = true;
+
+        let genome = Genome {
+            nodes: vec![],
+            genes: vec![
+                weighted(1, 5, 1.0, 1),
+                weighted(5, 5, 1.0, 2),
+                weighted(5, 0, 1.0, 3),
+            ],
+        };
+
+        let mut network = genome.build_network(&params);
+        let ins = [1.0, 0.0, 0.0, 0.0];
+        assert_eq!(network.calculate(&ins), vec![1.0]);
+        assert_eq!(network.calculate(&ins), vec![2.0]);
+        assert_eq!(network.calculate(&ins), vec![3.0]);
+
+        network.reset();
+        assert_eq!(network.calculate(&ins), vec![1.0]);
+
+        params.recurrent = false;
+        let mut network = genome.build_network(&params);
+        assert_eq!(network.calculate(&ins), vec![1.0]);
+        assert_eq!(network.calculate(&ins), vec![1.0]);
+    }
+
+    /// The recursive evaluator networks were calculated with before they were
+    /// compiled, used as a reference.
+    struct Reference {
+        params: Params,
+        genome: Genome,
+        incoming: Vec<Vec<(usize, f32)>>,
+        state: Vec<f32>,
+    }
+
+    #[derive(Clone, Copy)]
+    enum State {
+        White,
+        Gray,
+        Black(f32),
+    }
+
+    impl Reference {
+        fn new(genome: &Genome, params: Params) -> Reference {
+            let mut genes = genome.genes.clone();
+            genes.sort_by_key(|gene| gene.to);
+
+            let iter = genes.iter().filter(|gene| gene.enabled);
+            let node_max = iter.clone()
+                .map(|gene| max(gene.from, gene.to))
+                .max()
+                .unwrap_or(0);
+
+            let mut incoming =
+                vec![Vec::new(); max(node_max + 1, params.first_hidden())];
+            for gene in iter {
+                incoming[gene.to].push((gene.from, gene.weight));
+            }
+
+            Reference {
+                params,
+                genome: genome.clone(),
+                state: vec![0.0; incoming.len()],
+                incoming,
+            }
+        }
+
+        fn calculate(&mut self, ins: &[f32]) -> Vec<f32> {
+            let mut values = vec![State::White; self.incoming.len()];
+            for (i, &x) in ins.iter().enumerate() {
+                values[self.params.outputs + i] = State::Black(x);
+            }
+
+            fn search(n: usize, r: &Reference, values: &mut [State]) {
+                match values[n] {
+                    State::Black(_) | State::Gray => return,
+                    State::White => values[n] = State::Gray,
                 }
-                State::White => {
-                    values[n] = State::Gray;
+
+                for &(i, _) in &r.incoming[n] {
+                    search(i, r, values);
                 }
-            }
 
-            let neuron = &neurons[n];
-            for &(i, _) in &neuron.incoming {
-                search(i, activation, neurons, values);
+                let node = r.genome.node_or_default(n, &r.params);
+                let v = r.incoming[n]
+                    .iter()
+                    .map(|&(i, w)| {
+                        let x = match values[i] {
+                            State::Black(x) => x,
+                            _ => r.state[i],
+                        };
+                        x * w
+                    })
+                    .fold(node.bias, |a, b| a + b);
+                values[n] = State::Black(node.activation.calculate(v));
             }
 
-            let v = neuron
-                .incoming
-                .iter()
-                .map(|&(i, w)| values[i].to_option().unwrap() * w)
-                .sum();
-            values[n] = State::Black(v);
+            let outs = (0..self.params.outputs)
+                .map(|n| {
+                    search(n, self, &mut values);
+                    match values[n] {
+                        State::Black(x) => x,
+                        _ => 0.0,
+                    }
+                })
+                .collect();
+
+            if self.params.recurrent {
+                for (x, value) in self.state.iter_mut().zip(values) {
+                    if let State::Black(value) = value {
+                        *x = value;
+                    }
+                }
+            }
+            outs
         }
+    }
+
+    #[test]
+    fn matches_reference() {
+        let mut rng = XorShiftRng::new_unseeded();
+        for &recurrent in &[false, true] {
+            let mut params = params();
+            params.activation = Activation::Tanh;
+            params.output_activation = Some(Activation::Sigmoid);
+            params.outputs = 2;
+            params.recurrent = recurrent;
+
+            for _ in 0..50 {
+                let mut innovations =
+                    InnovationRegistry::new(params.first_hidden());
+                let mut genome = Genome::new(&params);
+                for _ in 0..rng.gen_range(0, 60) {
+                    genome.mutate(&mut rng, &mut innovations, &params);
+                }
 
-        search(0, self.activation, &self.neurons, &mut values);
-        values[0].to_option().unwrap_or(0.0)
+                let mut network = genome.build_network(&params);
+                let mut reference = Reference::new(&genome, params);
+                for _ in 0..5 {
+                    let ins = (0..params.inputs)
+                        .map(|_| rng.gen_range(-1.0, 1.0))
+                        .collect::<Vec<f32>>();
+                    let expected = reference.calculate(&ins);
+                    let actual = network.calculate(&ins);
+                    for (e, a) in expected.iter().zip(actual) {
+                        assert_eq!(e.to_bits(), a.to_bits());
+                    }
+                }
+            }
+        }
     }
-}
 
-#[derive(Clone, Debug, Default)]
-struct Neuron {
-    incoming: Vec<(usize, f32)>,
+    #[test]
+    fn node_genes() {
+        let mut params = params();
+        params.activation = Activation::ReLU;
+
+        let mut genome = genome();
+        genome.nodes = vec![
+            NodeGene {
+                id: 0,
+                bias: -0.1,
+                activation: Activation::ReLU,
+            },
+            NodeGene {
+                id: 5,
+                bias: 1.0,
+                activation: Activation::Tanh,
+            },
+        ];
+
+        let mut network = genome.build_network(&params);
+        let hidden = Activation::Tanh.calculate(1.0 - 0.5 - 0.125);
+        let expected = 2.0 * hidden + 0.25 - 0.1;
+        assert_eq!(network.calculate(&[0.5, -0.25, 1.0, 0.0]), vec![expected]);
+    }
 }
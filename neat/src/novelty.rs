@@ -0,0 +1,105 @@
+use std::cmp::min;
+
+use float_ord::FloatOrd;
+
+/// An archive of past behaviours, against which the novelty of new behaviours
+/// is measured.
+///
+/// A behaviour is any characterization of what an organism did, as a vector
+/// of numbers, such as a sampled trajectory. Behaviours of different lengths
+/// are compared as if the shorter one were padded with zeroes.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct NoveltyArchive {
+    behaviours: Vec<Vec<f32>>,
+}
+
+impl NoveltyArchive {
+    /// Creates a new, empty archive.
+    pub fn new() -> NoveltyArchive {
+        NoveltyArchive::default()
+    }
+
+    /// Returns the number of behaviours in the archive.
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    /// Returns whether the archive is empty.
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+
+    /// Returns the behaviours in the archive, in the order they were added.
+    pub fn behaviours(&self) -> &[Vec<f32>] {
+        &self.behaviours
+    }
+
+    /// Adds a behaviour to the archive.
+    pub fn add(&mut self, behaviour: Vec<f32>) {
+        self.behaviours.push(behaviour);
+    }
+
+    /// Returns the novelty of the `i`th of the given behaviours, which is the
+    /// mean distance to its `k` nearest neighbours among the archive and the
+    /// other given behaviours. If there are no neighbours, this is zero.
+    pub fn novelty(&self, behaviours: &[Vec<f32>], i: usize, k: usize) -> f32 {
+        let mut distances = self.behaviours
+            .iter()
+            .chain(
+                behaviours
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, b)| b),
+            )
+            .map(|b| distance(&behaviours[i], b))
+            .collect::<Vec<_>>();
+        distances.sort_by_key(|&d| FloatOrd(d));
+
+        let k = min(k, distances.len());
+        if k == 0 {
+            0.0
+        } else {
+            distances[..k].iter().sum::<f32>() / k as f32
+        }
+    }
+}
+
+/// Returns the Euclidean distance between two behaviours.
+pub fn distance(a: &[f32], b: &[f32]) -> f32 {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    long.iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let d = x - short.get(i).cloned().unwrap_or(0.0);
+            d * d
+        })
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{distance, NoveltyArchive};
+
+    #[test]
+    fn distance_pads_with_zeroes() {
+        assert_eq!(distance(&[3.0, 4.0], &[0.0, 0.0]), 5.0);
+        assert_eq!(distance(&[3.0], &[0.0, 4.0]), 5.0);
+        assert_eq!(distance(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn novelty_uses_nearest_neighbours() {
+        let mut archive = NoveltyArchive::new();
+        let behaviours = vec![vec![0.0], vec![1.0], vec![3.0]];
+        assert_eq!(archive.novelty(&behaviours, 0, 1), 1.0);
+        assert_eq!(archive.novelty(&behaviours, 0, 2), 2.0);
+        assert_eq!(archive.novelty(&behaviours, 2, 5), 2.5);
+
+        archive.add(vec![-1.0]);
+        assert_eq!(archive.novelty(&behaviours, 0, 2), 1.0);
+        assert_eq!(archive.novelty(&[vec![0.0]], 0, 3), 1.0);
+        assert_eq!(NoveltyArchive::new().novelty(&[vec![0.0]], 0, 3), 0.0);
+    }
+}
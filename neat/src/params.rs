@@ -1,3 +1,4 @@
+use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use activation::Activation;
@@ -18,39 +19,309 @@ pub struct Params {
     #[structopt(default_value = "0.4", long = "c3")]
     pub c3: f32,
 
-    /// The activation function to use. Valid values are: ReLU, Sigmoid, Tanh.
+    /// The weight for the mean difference between matching node genes in the
+    /// difference function.
+    #[structopt(default_value = "0.4", long = "c4")]
+    pub c4: f32,
+
+    /// The activation function to use. Valid values are: ReLU, Sigmoid, Tanh,
+    /// Sine, Gaussian, Abs, Step.
     #[serde(default, with = "::util::tofromstr")]
     #[structopt(default_value = "Sigmoid", long = "activation", short = "a")]
     pub activation: Activation,
 
+    /// Whether genomes are evolved as CPPNs, to be queried by a `Substrate`.
+    /// Mutations then choose from every activation function, rather than
+    /// only those suited to networks that are evolved directly.
+    #[structopt(long = "cppn")]
+    pub cppn: bool,
+
+    /// The chance an offspring is produced by crossover, rather than by copying
+    /// a single parent.
+    #[structopt(default_value = "0.75", long = "crossover-rate")]
+    pub crossover_rate: f32,
+
+    /// The activation function to use for the output neuron. If not present,
+    /// the activation function for other neurons is used.
+    #[serde(default, with = "::util::tofromstr_option")]
+    #[structopt(long = "output-activation")]
+    pub output_activation: Option<Activation>,
+
     /// The acceptable value for the difference function before two individuals
     /// are counted as being from a different species.
     #[structopt(default_value = "3.0", long = "delta-cutoff", short = "d")]
     pub delta_cutoff: f32,
 
+    /// The number of the fittest genomes in the whole population that are
+    /// copied unchanged into the next generation.
+    #[structopt(default_value = "0", long = "elitism-global")]
+    pub elitism_global: usize,
+
+    /// The minimum size of a species for its champion to be copied unchanged
+    /// into the next generation.
+    #[structopt(default_value = "5", long = "elitism-min-species-size")]
+    pub elitism_min_species_size: usize,
+
+    /// The number of the fittest genomes ever evaluated that are kept in the
+    /// hall of fame.
+    #[structopt(default_value = "10", long = "hall-of-fame-size")]
+    pub hall_of_fame_size: usize,
+
+    /// The number of the fittest genomes in the hall of fame that are copied
+    /// unchanged into the next generation, in place of offspring, if they
+    /// would not otherwise be in it.
+    #[structopt(default_value = "0", long = "hall-of-fame-elites")]
+    pub hall_of_fame_elites: usize,
+
+    /// The chance a gene disabled in either parent is disabled in the child.
+    #[structopt(default_value = "0.75", long = "inherit-disabled-rate")]
+    pub inherit_disabled_rate: f32,
+
+    /// Whether structural mutations are remembered across generations, rather
+    /// than for a single generation, when assigning innovation numbers.
+    #[structopt(long = "persist-innovations")]
+    pub persist_innovations: bool,
+
     /// The chance an individual is mutated.
     #[structopt(default_value = "0.5", long = "mutation-rate", short = "m")]
     pub mutation_rate: f32,
 
+    /// The chance a mutated individual gains a connection.
+    #[structopt(default_value = "0.2", long = "mutate-add-connection")]
+    pub mutate_add_connection: f32,
+
+    /// The chance a mutated individual gains a node.
+    #[structopt(default_value = "0.1", long = "mutate-add-node")]
+    pub mutate_add_node: f32,
+
+    /// The chance a mutated individual has its connection weights perturbed
+    /// uniformly.
+    #[structopt(default_value = "0.4", long = "mutate-reweight")]
+    pub mutate_reweight: f32,
+
+    /// The chance a mutated individual has its connection weights perturbed
+    /// by Gaussian noise.
+    #[structopt(default_value = "0.4", long = "mutate-gaussian")]
+    pub mutate_gaussian: f32,
+
+    /// The chance a mutated individual has a connection weight replaced.
+    #[structopt(default_value = "0.1", long = "mutate-replace-weight")]
+    pub mutate_replace_weight: f32,
+
+    /// The chance a mutated individual has a connection enabled or disabled.
+    #[structopt(default_value = "0.05", long = "mutate-toggle-enable")]
+    pub mutate_toggle_enable: f32,
+
+    /// The chance a mutated individual loses a connection.
+    #[structopt(default_value = "0.02", long = "mutate-delete-connection")]
+    pub mutate_delete_connection: f32,
+
+    /// The chance a mutated individual loses a hidden node that has no enabled
+    /// incoming or no enabled outgoing connections.
+    #[structopt(default_value = "0.05", long = "mutate-delete-orphan-node")]
+    pub mutate_delete_orphan_node: f32,
+
+    /// The chance a mutated individual has its node biases perturbed.
+    #[structopt(default_value = "0.2", long = "mutate-bias")]
+    pub mutate_bias: f32,
+
+    /// The chance a mutated individual has a node's activation function
+    /// changed.
+    #[structopt(default_value = "0.05", long = "mutate-activation")]
+    pub mutate_activation: f32,
+
+    /// The standard deviation of the noise added to a gene by a Gaussian
+    /// mutation.
+    #[structopt(default_value = "0.25", long = "gaussian-stddev")]
+    pub gaussian_stddev: f32,
+
+    /// The maximum magnitude of a weight chosen by a weight replacement
+    /// mutation.
+    #[structopt(default_value = "2.0", long = "weight-range")]
+    pub weight_range: f32,
+
+    /// The number of nearest neighbours whose behaviours are compared when
+    /// measuring novelty.
+    #[structopt(default_value = "15", long = "novelty-k")]
+    pub novelty_k: usize,
+
+    /// The chance each behaviour is added to the novelty archive.
+    #[structopt(default_value = "0.02", long = "novelty-archive-rate")]
+    pub novelty_archive_rate: f32,
+
+    /// How much selection in novelty search is based on novelty rather than
+    /// fitness, from 0 (only fitness) to 1 (only novelty).
+    #[structopt(default_value = "1.0", long = "novelty-weight")]
+    pub novelty_weight: f32,
+
+    /// Whether networks are recurrent, keeping their state between steps. If
+    /// not, mutations never create cycles.
+    #[structopt(long = "recurrent")]
+    pub recurrent: bool,
+
     /// The maximum magnitude by which a reweight mutation affects a gene.
     #[structopt(default_value = "0.5", long = "reweight-amount")]
     pub reweight_amount: f32,
 
-    /// The chance an individual gene is mutated during a reweight mutation.
+    /// The chance an individual gene or node is mutated during a reweight,
+    /// Gaussian or bias mutation.
     #[structopt(default_value = "0.5", long = "reweight-rate")]
     pub reweight_rate: f32,
 
+    /// The number of input neurons in each network.
+    #[structopt(default_value = "4", long = "inputs")]
+    pub inputs: usize,
+
+    /// The number of output neurons in each network.
+    #[structopt(default_value = "1", long = "outputs")]
+    pub outputs: usize,
+
     /// The number of members in the population.
     #[structopt(default_value = "300", long = "population", short = "p")]
     pub population_size: usize,
+
+    /// The number of generations a species may go without improving its best
+    /// fitness before it is culled.
+    #[structopt(default_value = "15", long = "stagnation-limit")]
+    pub stagnation_limit: usize,
+
+    /// The number of species, ranked by best fitness, that are never culled
+    /// for stagnation.
+    #[structopt(default_value = "2", long = "stagnation-protected")]
+    pub stagnation_protected: usize,
+
+    /// Whether every offspring is checked for validity after crossover and
+    /// after each mutation. An invalid genome stops the generation with an
+    /// error naming the operator that produced it.
+    #[structopt(long = "strict")]
+    pub strict: bool,
+
+    /// The fraction of each species, by fitness, that is allowed to reproduce.
+    #[structopt(default_value = "0.2", long = "survival-threshold")]
+    pub survival_threshold: f32,
+}
+
+impl Params {
+    /// Checks that the parameters can be used for training.
+    pub fn check(&self) -> Result<(), ParamsError> {
+        let positive = [
+            ("reweight-amount", self.reweight_amount),
+            ("weight-range", self.weight_range),
+        ];
+        for &(name, value) in &positive {
+            if value.is_nan() || value <= 0.0 {
+                return Err(ParamsError::NotPositive(name, value));
+            }
+        }
+
+        let stddev = self.gaussian_stddev;
+        if stddev.is_nan() || stddev < 0.0 {
+            return Err(ParamsError::Negative("gaussian-stddev", stddev));
+        }
+        Ok(())
+    }
+
+    /// Returns whether the node with the given ID is an output node. Output
+    /// nodes come first, starting at ID 0.
+    pub fn is_output(&self, node: usize) -> bool {
+        node < self.outputs
+    }
+
+    /// Returns whether the node with the given ID is an input node. Input
+    /// nodes immediately follow the output nodes.
+    pub fn is_input(&self, node: usize) -> bool {
+        node >= self.outputs && node < self.first_hidden()
+    }
+
+    /// Returns the ID of the first hidden node. All later IDs are hidden
+    /// nodes.
+    pub fn first_hidden(&self) -> usize {
+        self.outputs + self.inputs
+    }
 }
 
 impl Display for Params {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
         write!(
             fmt,
-            "c1 = {}, c2 = {}, c3 = {}, activation = {}, delta_cutoff = {}, population_size = {}",
-            self.c1, self.c2, self.c3, self.activation, self.delta_cutoff, self.population_size,
+            "c1 = {}, c2 = {}, c3 = {}, c4 = {}, activation = {}, cppn = {}, \
+             output_activation = {:?}, delta_cutoff = {}, elitism_global = {}, \
+             elitism_min_species_size = {}, hall_of_fame_size = {}, \
+             hall_of_fame_elites = {}, persist_innovations = {}, \
+             mutate_add_connection = {}, mutate_add_node = {}, \
+             mutate_reweight = {}, mutate_gaussian = {}, \
+             mutate_replace_weight = {}, mutate_toggle_enable = {}, \
+             mutate_delete_connection = {}, mutate_delete_orphan_node = {}, \
+             mutate_bias = {}, mutate_activation = {}, gaussian_stddev = {}, \
+             weight_range = {}, novelty_k = {}, novelty_archive_rate = {}, \
+             novelty_weight = {}, recurrent = {}, inputs = {}, outputs = {}, \
+             population_size = {}, stagnation_limit = {}, \
+             stagnation_protected = {}, strict = {}",
+            self.c1,
+            self.c2,
+            self.c3,
+            self.c4,
+            self.activation,
+            self.cppn,
+            self.output_activation,
+            self.delta_cutoff,
+            self.elitism_global,
+            self.elitism_min_species_size,
+            self.hall_of_fame_size,
+            self.hall_of_fame_elites,
+            self.persist_innovations,
+            self.mutate_add_connection,
+            self.mutate_add_node,
+            self.mutate_reweight,
+            self.mutate_gaussian,
+            self.mutate_replace_weight,
+            self.mutate_toggle_enable,
+            self.mutate_delete_connection,
+            self.mutate_delete_orphan_node,
+            self.mutate_bias,
+            self.mutate_activation,
+            self.gaussian_stddev,
+            self.weight_range,
+            self.novelty_k,
+            self.novelty_archive_rate,
+            self.novelty_weight,
+            self.recurrent,
+            self.inputs,
+            self.outputs,
+            self.population_size,
+            self.stagnation_limit,
+            self.stagnation_protected,
+            self.strict,
         )
     }
 }
+
+/// A parameter that has a value training cannot use. The parameters are the
+/// name of the option, without leading dashes, and its value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamsError {
+    /// The option must be greater than zero.
+    NotPositive(&'static str, f32),
+
+    /// The option must not be less than zero.
+    Negative(&'static str, f32),
+}
+
+impl Display for ParamsError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            ParamsError::NotPositive(name, value) => {
+                write!(fmt, "--{} must be positive, not {}", name, value)
+            }
+            ParamsError::Negative(name, value) => {
+                write!(fmt, "--{} must not be negative, not {}", name, value)
+            }
+        }
+    }
+}
+
+impl Error for ParamsError {
+    fn description(&self) -> &str {
+        "invalid parameters"
+    }
+}
@@ -0,0 +1,145 @@
+//! Multi-objective selection, as in NSGA-II by Deb et al. Every objective is
+//! maximized.
+
+use std::f32;
+
+use float_ord::FloatOrd;
+
+/// Returns whether `a` dominates `b`, being at least as good in every
+/// objective and better in at least one.
+pub fn dominates(a: &[f32], b: &[f32]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y)
+        && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// Sorts the given points into non-dominated fronts, returning the indices
+/// of the points in each front. The first front is the Pareto front, and no
+/// point in a front is dominated by any point in the same or later fronts.
+pub fn non_dominated_sort(objectives: &[Vec<f32>]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated_by = vec![0; n];
+    let mut dominates_list = vec![Vec::new(); n];
+    for i in 0..n {
+        for j in 0..n {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominates_list[i].push(j);
+            } else if dominates(&objectives[j], &objectives[i]) {
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front = (0..n)
+        .filter(|&i| dominated_by[i] == 0)
+        .collect::<Vec<_>>();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &i in &front {
+            for &j in &dominates_list[i] {
+                dominated_by[j] -= 1;
+                if dominated_by[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        next.sort();
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+/// Returns the crowding distance of each point in a front, in the same order
+/// as `front`. Points at the extremes of any objective have an infinite
+/// distance.
+pub fn crowding_distance(objectives: &[Vec<f32>], front: &[usize]) -> Vec<f32> {
+    let mut distances = vec![0.0; front.len()];
+    let dimensions = front.first().map(|&i| objectives[i].len()).unwrap_or(0);
+    let columns = (0..dimensions)
+        .map(|m| front.iter().map(|&i| objectives[i][m]).collect::<Vec<_>>());
+    for values in columns {
+        let mut order = (0..front.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&k| FloatOrd(values[k]));
+
+        let first = order[0];
+        let last = order[order.len() - 1];
+        let range = values[last] - values[first];
+        distances[first] = f32::INFINITY;
+        distances[last] = f32::INFINITY;
+        if range == 0.0 {
+            continue;
+        }
+        for w in order.windows(3) {
+            distances[w[1]] += (values[w[2]] - values[w[0]]) / range;
+        }
+    }
+    distances
+}
+
+/// Converts the objectives of each point to a single score, such that a point
+/// in an earlier front always scores higher than one in a later front, and
+/// points in the same front score higher the greater their crowding
+/// distance. Scores are never negative.
+pub fn scores(objectives: &[Vec<f32>]) -> Vec<f32> {
+    let fronts = non_dominated_sort(objectives);
+    let mut scores = vec![0.0; objectives.len()];
+    for (rank, front) in fronts.iter().enumerate() {
+        let base = 2.0 * (fronts.len() - rank) as f32;
+        let distances = crowding_distance(objectives, front);
+        for (&i, &d) in front.iter().zip(&distances) {
+            let crowding = if d.is_infinite() { 1.0 } else { d / (1.0 + d) };
+            scores[i] = base + crowding;
+        }
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32;
+
+    use super::{crowding_distance, dominates, non_dominated_sort, scores};
+
+    fn points() -> Vec<Vec<f32>> {
+        vec![
+            vec![1.0, 5.0],
+            vec![2.0, 4.0],
+            vec![1.0, 1.0],
+            vec![3.0, 1.0],
+            vec![2.0, 2.0],
+            vec![4.0, 1.0],
+        ]
+    }
+
+    #[test]
+    fn dominance() {
+        assert!(dominates(&[2.0, 2.0], &[1.0, 2.0]));
+        assert!(!dominates(&[2.0, 2.0], &[2.0, 2.0]));
+        assert!(!dominates(&[2.0, 1.0], &[1.0, 2.0]));
+    }
+
+    #[test]
+    fn sort_into_fronts() {
+        assert_eq!(
+            non_dominated_sort(&points()),
+            vec![vec![0, 1, 5], vec![3, 4], vec![2]]
+        );
+        assert_eq!(non_dominated_sort(&[]), Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn crowding() {
+        let distances = crowding_distance(&points(), &[0, 1, 5]);
+        assert_eq!(distances, vec![f32::INFINITY, 2.0, f32::INFINITY]);
+    }
+
+    #[test]
+    fn scores_respect_fronts() {
+        let scores = scores(&points());
+        assert!(scores[1] > scores[4]);
+        assert!(scores[0] > scores[1]);
+        assert!(scores[3] > scores[2]);
+        assert!(scores.iter().all(|&s| s >= 0.0));
+    }
+}
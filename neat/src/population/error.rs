@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use genome::{GenomeError, Mutation};
+
+/// An error running a generation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerationError<E> {
+    /// Evaluating an individual failed with the given error.
+    Evaluation(E),
+
+    /// In strict mode, an offspring was invalid after crossover, if the
+    /// mutation is `None`, or after the given mutation.
+    InvalidGenome(Option<Mutation>, GenomeError),
+}
+
+impl<E: Display> Display for GenerationError<E> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            GenerationError::Evaluation(ref err) => err.fmt(fmt),
+            GenerationError::InvalidGenome(None, err) => {
+                write!(fmt, "crossover produced an invalid genome: {}", err)
+            }
+            GenerationError::InvalidGenome(Some(mutation), err) => write!(
+                fmt,
+                "{:?} mutation produced an invalid genome: {}",
+                mutation, err
+            ),
+        }
+    }
+}
+
+impl<E: Error> Error for GenerationError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            GenerationError::Evaluation(_) => "evaluation failed",
+            GenerationError::InvalidGenome(_, _) => "invalid genome",
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use genome::Genome;
+
+/// A genome from a previous generation, along with the result of evaluating
+/// it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Evaluation {
+    /// The genome that was evaluated.
+    pub genome: Genome,
+
+    /// The fitness the genome was given. For novelty search, this is the
+    /// fitness before it was blended with the novelty score, and for
+    /// multi-objective runs, it is the first objective.
+    pub fitness: f32,
+
+    /// The seed of the RNG the genome was evaluated with, if it was given its
+    /// own, as by `Population::run_generation_parallel`. Evaluating the genome
+    /// again with an RNG created from this seed reproduces its fitness.
+    pub seed: Option<[u32; 4]>,
+}
@@ -1,36 +1,95 @@
+mod error;
+mod evaluation;
 mod iter;
+mod parallel;
+mod stats;
 
+use std::cmp::{max, min, Reverse};
 use std::ops::{Index, IndexMut};
 
-use rand::Rng;
+use float_ord::FloatOrd;
+use rand::{Rng, SeedableRng, XorShiftRng};
 
 use crossover::classify_species;
 use genome::Genome;
-use params::Params;
+use hall_of_fame::HallOfFame;
+use innovation::InnovationRegistry;
+use novelty::NoveltyArchive;
+use params::{Params, ParamsError};
+use pareto;
 use species::Species;
 
+pub use self::error::GenerationError;
+pub use self::evaluation::Evaluation;
 pub use self::iter::PopulationIter;
+pub use self::stats::{GenerationStats, SpeciesStats};
 
 /// A collection of organisms that can be evaluated and trained as one.
+///
+/// A population records the seed it was created with, and the state of an
+/// RNG that is advanced by each generation. Running every generation with the
+/// RNG from `rng` makes training fully reproducible.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Population {
     /// The parameters used for the next generation.
     pub params: Params,
 
+    archive: NoveltyArchive,
+    evaluations: Vec<Evaluation>,
     generation: usize,
-    innovation: usize,
+    hall_of_fame: HallOfFame,
+    innovations: InnovationRegistry,
+    next_species: usize,
+    pareto_front: Vec<(Genome, Vec<f32>)>,
+    rng_state: [u32; 4],
+    seed: u64,
     species: Vec<Species>,
 }
 
 impl Population {
-    /// Creates a new population with the given parameters.
-    pub fn new(params: Params) -> Population {
-        Population {
+    /// Creates a new population with the given parameters and a seed of
+    /// zero, or returns an error if the parameters cannot be used for
+    /// training.
+    pub fn new(params: Params) -> Result<Population, ParamsError> {
+        Population::with_seed(params, 0)
+    }
+
+    /// Creates a new population with the given parameters and seed, or
+    /// returns an error if the parameters cannot be used for training.
+    pub fn with_seed(
+        params: Params,
+        seed: u64,
+    ) -> Result<Population, ParamsError> {
+        params.check()?;
+        Ok(Population {
+            archive: NoveltyArchive::new(),
+            evaluations: Vec::new(),
             generation: 0,
-            innovation: 0,
+            hall_of_fame: HallOfFame::new(),
+            innovations: InnovationRegistry::new(params.first_hidden()),
+            next_species: 1,
             params,
-            species: vec![Species::with_size(params.population_size)],
-        }
+            pareto_front: Vec::new(),
+            rng_state: [
+                seed as u32,
+                (seed >> 32) as u32,
+                0x9E37_79B9,
+                0x7F4A_7C15,
+            ],
+            seed,
+            species: vec![Species::with_size(0, &params)],
+        })
+    }
+
+    /// Returns the seed the population was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns an RNG for running the next generation, in the state left by
+    /// the previous one.
+    pub fn rng(&self) -> XorShiftRng {
+        XorShiftRng::from_seed(self.rng_state)
     }
 
     /// Returns the generation number this population is at.
@@ -38,58 +97,506 @@ impl Population {
         self.generation
     }
 
+    /// Returns the archive of behaviours used by novelty search.
+    pub fn archive(&self) -> &NoveltyArchive {
+        &self.archive
+    }
+
+    /// Returns the fittest genomes evaluated in any previous generation.
+    pub fn hall_of_fame(&self) -> &HallOfFame {
+        &self.hall_of_fame
+    }
+
+    /// Returns the genomes on the Pareto front of the previous generation,
+    /// along with their objectives, if it was run by
+    /// `run_generation_multi_objective`.
+    pub fn pareto_front(&self) -> &[(Genome, Vec<f32>)] {
+        &self.pareto_front
+    }
+
+    /// Returns the genomes of the previous generation along with their
+    /// fitnesses, in the order they were evaluated.
+    pub fn evaluations(&self) -> &[Evaluation] {
+        &self.evaluations
+    }
+
+    /// Returns the genomes of the previous generation along with their
+    /// fitnesses, fittest first.
+    pub fn ranked_evaluations(&self) -> Vec<&Evaluation> {
+        let mut ranked = self.evaluations.iter().collect::<Vec<_>>();
+        ranked.sort_by_key(|e| Reverse(FloatOrd(e.fitness)));
+        ranked
+    }
+
+    /// Returns the fittest genome of the previous generation, if there was
+    /// one.
+    pub fn champion(&self) -> Option<&Evaluation> {
+        self.evaluations
+            .iter()
+            .max_by_key(|e| FloatOrd(e.fitness))
+    }
+
     /// Returns the number of individuals in the population.
     pub fn len(&self) -> usize {
         self.species.iter().map(|s| s.len()).sum()
     }
 
-    /// Mutates a population.
-    fn mutate<I: FnMut() -> usize, R: Rng>(&mut self, r: &mut R, mut inno: I) {
-        for i in 0..self.len() {
-            if r.next_f32() < self.params.mutation_rate {
-                // TODO Avoid the clone.
-                let mut genome = self[i].clone();
-                genome.mutate(r, &mut inno, &self.params);
-                self[i] = genome;
+    /// Mutates the given genomes, allocating innovation numbers from the
+    /// population's registry. Unless `params.persist_innovations` is set, only
+    /// mutations from the same generation share innovation numbers.
+    ///
+    /// If `params.strict` is set, every genome is checked before mutation and
+    /// after each mutation, and an error naming the operator that made it
+    /// invalid is returned.
+    ///
+    /// Returns the number of new structural innovations, which are
+    /// add-connection and add-node mutations not already in the registry.
+    fn mutate<E, R: Rng>(
+        &mut self,
+        r: &mut R,
+        genomes: &mut [Genome],
+    ) -> Result<usize, GenerationError<E>> {
+        if !self.params.persist_innovations {
+            self.innovations.clear();
+        }
+        let recorded = self.innovations.len();
+        for genome in genomes {
+            if self.params.strict {
+                genome
+                    .check(&self.params)
+                    .map_err(|err| GenerationError::InvalidGenome(None, err))?;
+            }
+            if r.next_f32() >= self.params.mutation_rate {
+                continue;
             }
+            if !self.params.strict {
+                genome.mutate(r, &mut self.innovations, &self.params);
+                continue;
+            }
+
+            genome
+                .mutate_checked(r, &mut self.innovations, &self.params)
+                .map_err(|(mutation, err)| {
+                    GenerationError::InvalidGenome(Some(mutation), err)
+                })?;
         }
+        Ok(self.innovations.len() - recorded)
     }
 
     /// Runs a single generation. The given function evaluates an individual's
     /// fitness.
+    ///
+    /// Species that have stagnated for too long are culled. Fitnesses are
+    /// shared within each remaining species, and every species then reproduces
+    /// from its fittest members in proportion to its total adjusted fitness.
+    /// The resulting offspring are mutated and speciated to form the returned
+    /// population, alongside the elites, which are copied unchanged. Statistics
+    /// on the evaluated generation are returned with it.
+    ///
+    /// The evaluated genomes are added to the hall of fame, and the
+    /// `params.hall_of_fame_elites` fittest genomes in it are copied into the
+    /// returned population in place of offspring, if they would not otherwise
+    /// be in it.
     pub fn run_generation<E, F, R>(
         &self,
         r: &mut R,
-        fitness: F,
-    ) -> Result<Population, E>
+        mut fitness: F,
+    ) -> Result<(Population, GenerationStats), GenerationError<E>>
     where
         F: FnMut(&Genome) -> Result<f32, E>,
         R: Rng,
     {
-        let mut pop = self.clone();
-        pop.generation += 1;
+        let fitnesses = self.species
+            .iter()
+            .map(|s| s.into_iter().map(&mut fitness).collect())
+            .collect::<Result<Vec<Vec<f32>>, E>>()
+            .map_err(GenerationError::Evaluation)?;
+        self.next_generation(r, fitnesses.clone(), &fitnesses, None)
+    }
 
-        let mut inno = pop.innovation;
-        pop.mutate(r, || {
-            inno += 1;
-            inno
-        });
-        pop.innovation = inno;
+    /// Runs a single generation like `run_generation`, but evaluates fitnesses
+    /// on `jobs` threads.
+    ///
+    /// Each individual's fitness function is given its own RNG, seeded from
+    /// `r`, so the result is the same for any number of threads. The threads
+    /// only live for the call, so the function may borrow from its caller.
+    /// Once an evaluation fails, no more are started.
+    pub fn run_generation_parallel<E, F, R>(
+        &self,
+        r: &mut R,
+        jobs: usize,
+        fitness: F,
+    ) -> Result<(Population, GenerationStats), GenerationError<E>>
+    where
+        E: Send,
+        F: Fn(&Genome, &mut XorShiftRng) -> Result<f32, E> + Sync,
+        R: Rng,
+    {
+        let genomes = self.into_iter().collect::<Vec<_>>();
+        let seeds = genomes.iter().map(|_| new_seed(r)).collect::<Vec<_>>();
+
+        let mut results = parallel::evaluate(&genomes, &seeds, jobs, &fitness)
+            .map_err(GenerationError::Evaluation)?
+            .into_iter();
+        let fitnesses = self.species
+            .iter()
+            .map(|s| results.by_ref().take(s.len()).collect())
+            .collect::<Vec<Vec<f32>>>();
+        self.next_generation(r, fitnesses.clone(), &fitnesses, Some(&seeds))
+    }
+
+    /// Runs a single generation using novelty search. The given function
+    /// evaluates an individual's fitness and characterizes its behaviour.
+    ///
+    /// Each individual is scored by the novelty of its behaviour compared to
+    /// the rest of the population and the archive, as measured by
+    /// `NoveltyArchive::novelty` with `params.novelty_k` neighbours. The score
+    /// is blended with its fitness according to `params.novelty_weight`, and
+    /// the blended scores are shared and used to allocate offspring as in
+    /// `run_generation`. Stagnation and elitism still follow the unblended
+    /// fitnesses, since novelty falls as the archive fills. Afterwards, each
+    /// behaviour is added to the archive with a chance of
+    /// `params.novelty_archive_rate`.
+    pub fn run_generation_novelty<E, F, R>(
+        &self,
+        r: &mut R,
+        mut evaluate: F,
+    ) -> Result<(Population, GenerationStats), GenerationError<E>>
+    where
+        F: FnMut(&Genome) -> Result<(f32, Vec<f32>), E>,
+        R: Rng,
+    {
+        let mut fitnesses = Vec::with_capacity(self.species.len());
+        let mut behaviours = Vec::with_capacity(self.len());
+        for s in &self.species {
+            let mut species_fitnesses = Vec::with_capacity(s.len());
+            for genome in s {
+                let (fitness, behaviour) =
+                    evaluate(genome).map_err(GenerationError::Evaluation)?;
+                species_fitnesses.push(fitness);
+                behaviours.push(behaviour);
+            }
+            fitnesses.push(species_fitnesses);
+        }
+
+        let weight = self.params.novelty_weight;
+        let mut scores = fitnesses.clone();
+        let flat = scores.iter_mut().flat_map(|s| s.iter_mut());
+        for (i, score) in flat.enumerate() {
+            let novelty =
+                self.archive.novelty(&behaviours, i, self.params.novelty_k);
+            *score = weight * novelty + (1.0 - weight) * *score;
+        }
+
+        let (mut pop, stats) =
+            self.next_generation(r, scores, &fitnesses, None)?;
+        for behaviour in behaviours {
+            if r.next_f32() < self.params.novelty_archive_rate {
+                pop.archive.add(behaviour);
+            }
+        }
+        Ok((pop, stats))
+    }
+
+    /// Runs a single generation with multiple objectives. The given function
+    /// evaluates each of an individual's objectives, all of which are
+    /// maximized, such as its fitness and its negated size.
+    ///
+    /// Individuals are sorted into non-dominated fronts and ranked by crowding
+    /// distance within each front, as in NSGA-II. The ranks are converted to
+    /// scores such that an earlier front always scores higher, and the scores
+    /// are shared and used to allocate offspring as in `run_generation`. Since
+    /// the scores cannot be compared between generations, stagnation and
+    /// elitism follow the first objective instead. The first front is kept in
+    /// the returned population.
+    pub fn run_generation_multi_objective<E, F, R>(
+        &self,
+        r: &mut R,
+        mut objectives: F,
+    ) -> Result<(Population, GenerationStats), GenerationError<E>>
+    where
+        F: FnMut(&Genome) -> Result<Vec<f32>, E>,
+        R: Rng,
+    {
+        let evaluated = self.into_iter()
+            .map(&mut objectives)
+            .collect::<Result<Vec<_>, E>>()
+            .map_err(GenerationError::Evaluation)?;
+
+        let mut scores = pareto::scores(&evaluated).into_iter();
+        let mut first = evaluated
+            .iter()
+            .map(|objectives| objectives.first().cloned().unwrap_or(0.0));
+        let (scores, fitnesses) = self.species
+            .iter()
+            .map(|s| {
+                let scores = scores.by_ref().take(s.len()).collect();
+                let fitnesses = first.by_ref().take(s.len()).collect();
+                (scores, fitnesses)
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
 
-        let genomes = pop.species
+        let front = pareto::non_dominated_sort(&evaluated)
             .into_iter()
-            .flat_map(|s| s.0)
-            .collect::<Vec<Genome>>();
+            .next()
+            .unwrap_or_default();
+        let (mut pop, stats) =
+            self.next_generation(r, scores, &fitnesses, None)?;
+        pop.pareto_front = front
+            .into_iter()
+            .map(|i| (self[i].clone(), evaluated[i].clone()))
+            .collect();
+        Ok((pop, stats))
+    }
 
-        let fitnesses = genomes
+    /// Produces the next generation from the scores and fitnesses of the
+    /// current one, grouped by species, and reports statistics on the current
+    /// one's fitnesses. The scores are shared and used to allocate offspring,
+    /// while the fitnesses track stagnation and pick elites. The fitnesses are
+    /// also recorded in the next generation's evaluations, along with `seeds`,
+    /// which are in the same order as the population. The next generation's
+    /// RNG state is drawn from `r`.
+    fn next_generation<E, R: Rng>(
+        &self,
+        r: &mut R,
+        scores: Vec<Vec<f32>>,
+        fitnesses: &[Vec<f32>],
+        seeds: Option<&[[u32; 4]]>,
+    ) -> Result<(Population, GenerationStats), GenerationError<E>> {
+        let mut species = self.species
             .iter()
-            .map(fitness)
-            .collect::<Result<Vec<f32>, E>>()?;
+            .cloned()
+            .zip(fitnesses.iter().cloned().zip(scores))
+            .map(|(mut s, (fitnesses, scores))| {
+                s.update_fitness(&fitnesses);
+                (s, (fitnesses, scores))
+            })
+            .collect::<Vec<_>>();
+        cull_stagnant(&mut species, &self.params);
+        let (species, scores): (Vec<_>, Vec<_>) = species
+            .into_iter()
+            .map(|(s, (fitnesses, scores))| ((s, fitnesses), scores))
+            .unzip();
+
+        let adjusted = species
+            .iter()
+            .zip(scores)
+            .map(|((s, _), scores)| share_fitness(s.len(), scores))
+            .collect::<Vec<_>>();
+
+        let sums = adjusted
+            .iter()
+            .map(|fitnesses| fitnesses.iter().sum())
+            .collect::<Vec<f32>>();
+        let global = global_elites(
+            &species,
+            min(self.params.elitism_global, self.params.population_size),
+        );
+
+        let mut hall_of_fame = self.hall_of_fame.clone();
+        let evaluated = fitnesses.iter().flat_map(|f| f.iter().cloned());
+        for (genome, fitness) in self.into_iter().zip(evaluated) {
+            hall_of_fame.insert(
+                genome,
+                fitness,
+                self.generation,
+                self.params.hall_of_fame_size,
+            );
+        }
+
+        let counts = allocate_offspring(
+            &sums,
+            self.params.population_size - global.len(),
+        );
+
+        let mut elites = global
+            .iter()
+            .map(|&(i, j)| species[i].0[j].clone())
+            .collect::<Vec<_>>();
+        let mut offspring = Vec::with_capacity(self.params.population_size);
+        for (i, (((s, fitnesses), adjusted), mut n)) in
+            species.iter().zip(adjusted).zip(counts).enumerate()
+        {
+            if n > 0 && s.len() >= self.params.elitism_min_species_size {
+                let champion = s.champion(fitnesses);
+                if !global.contains(&(i, champion)) {
+                    elites.push(s[champion].clone());
+                    n -= 1;
+                }
+            }
+            offspring.extend(s.reproduce(r, &adjusted, n, &self.params));
+        }
+
+        let mut pop = self.clone();
+        pop.generation += 1;
+        pop.hall_of_fame = hall_of_fame;
+        let innovations = pop.mutate(r, &mut offspring)?;
+
+        // Re-inject the fittest genomes in the hall of fame that did not make
+        // it into the next generation, replacing random offspring.
+        let lost = pop.hall_of_fame
+            .entries()
+            .iter()
+            .take(self.params.hall_of_fame_elites)
+            .filter(|e| {
+                !elites.iter().chain(&offspring).any(|g| *g == e.genome)
+            })
+            .take(offspring.len())
+            .map(|e| e.genome.clone())
+            .collect::<Vec<_>>();
+        for _ in 0..lost.len() {
+            let i = r.gen_range(0, offspring.len());
+            offspring.swap_remove(i);
+        }
+        elites.extend(lost);
+        elites.extend(offspring);
+        pop.species = classify_species(
+            elites,
+            &self.params,
+            species.into_iter().map(|(s, _)| s).collect(),
+            &mut pop.next_species,
+            pop.generation,
+        );
+        pop.rng_state = new_seed(r);
+        pop.evaluations = self.into_iter()
+            .cloned()
+            .zip(fitnesses.iter().flat_map(|f| f.iter().cloned()))
+            .enumerate()
+            .map(|(n, (genome, fitness))| Evaluation {
+                genome,
+                fitness,
+                seed: seeds.map(|seeds| seeds[n]),
+            })
+            .collect();
+        let stats = self.stats(fitnesses, innovations);
+        Ok((pop, stats))
+    }
+
+    /// Returns statistics on this generation, given its fitnesses grouped by
+    /// species and the number of new structural innovations made while
+    /// producing the next generation.
+    fn stats(
+        &self,
+        fitnesses: &[Vec<f32>],
+        innovations: usize,
+    ) -> GenerationStats {
+        let species = self.species
+            .iter()
+            .zip(fitnesses)
+            .map(|(s, fitnesses)| (s.id, &fitnesses[..]))
+            .collect::<Vec<_>>();
+        let lengths = self.into_iter()
+            .map(|g| g.len() as f32)
+            .collect::<Vec<_>>();
+        let first_hidden = self.params.first_hidden();
+        let hidden = self.into_iter()
+            .map(|g| {
+                let nodes = g.nodes(&self.params);
+                nodes.iter().filter(|&&n| n >= first_hidden).count() as f32
+            })
+            .collect::<Vec<_>>();
+        GenerationStats::new(
+            self.generation,
+            &species,
+            stats::mean(&lengths),
+            stats::mean(&hidden),
+            innovations,
+        )
+    }
+}
+
+/// Returns a random seed for an XorShiftRng.
+fn new_seed<R: Rng>(r: &mut R) -> [u32; 4] {
+    // An all-zero seed is invalid.
+    let mut seed: [u32; 4] = r.gen();
+    seed[0] |= 1;
+    seed
+}
+
+/// Removes species that have gone `params.stagnation_limit` generations
+/// without improving, except for the `params.stagnation_protected` species
+/// with the best fitness. The best species is always kept, so the population
+/// never dies out entirely.
+fn cull_stagnant<T>(species: &mut Vec<(Species, T)>, params: &Params) {
+    let mut ranked = (0..species.len()).collect::<Vec<_>>();
+    ranked.sort_by_key(|&i| Reverse(FloatOrd(species[i].0.best_fitness)));
+    let protected = ranked
+        .into_iter()
+        .take(max(params.stagnation_protected, 1))
+        .map(|i| species[i].0.id)
+        .collect::<Vec<_>>();
+
+    species.retain(|(s, _)| {
+        s.stagnation < params.stagnation_limit || protected.contains(&s.id)
+    });
+}
+
+/// Returns the positions of the `n` fittest genomes across all species, as
+/// pairs of species and member indices.
+fn global_elites(
+    species: &[(Species, Vec<f32>)],
+    n: usize,
+) -> Vec<(usize, usize)> {
+    let mut ranked = species
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, fitnesses))| {
+            fitnesses
+                .iter()
+                .enumerate()
+                .map(move |(j, &fitness)| (i, j, fitness))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|&(_, _, fitness)| Reverse(FloatOrd(fitness)));
+    ranked.into_iter().take(n).map(|(i, j, _)| (i, j)).collect()
+}
+
+/// Divides each fitness by the size of its species, giving the adjusted
+/// fitness used by explicit fitness sharing. Negative fitnesses are treated as
+/// zero.
+fn share_fitness(species_size: usize, mut fitnesses: Vec<f32>) -> Vec<f32> {
+    for fitness in fitnesses.iter_mut() {
+        *fitness = fitness.max(0.0) / species_size as f32;
+    }
+    fitnesses
+}
+
+/// Splits `total` offspring between species in proportion to their summed
+/// adjusted fitnesses, using the largest remainder method so the counts add up
+/// to exactly `total`. If no species has any fitness, the offspring are split
+/// evenly.
+fn allocate_offspring(sums: &[f32], total: usize) -> Vec<usize> {
+    if sums.is_empty() {
+        return Vec::new();
+    }
+
+    let sum_total: f32 = sums.iter().sum();
+    let shares = if sum_total > 0.0 {
+        sums.iter()
+            .map(|&sum| sum / sum_total * total as f32)
+            .collect::<Vec<_>>()
+    } else {
+        vec![total as f32 / sums.len() as f32; sums.len()]
+    };
 
-        pop.species = classify_species(r, genomes, &self.params, &self.species);
-        // TODO: Actually crossover.
-        Ok(pop)
+    let mut counts = shares
+        .iter()
+        .map(|&share| share.floor() as usize)
+        .collect::<Vec<_>>();
+    let mut by_remainder = (0..sums.len()).collect::<Vec<_>>();
+    by_remainder.sort_by_key(|&i| {
+        Reverse(FloatOrd(shares[i] - counts[i] as f32))
+    });
+
+    let allocated: usize = counts.iter().sum();
+    for i in by_remainder
+        .into_iter()
+        .cycle()
+        .take(total.saturating_sub(allocated))
+    {
+        counts[i] += 1;
     }
+    counts
 }
 
 impl Index<usize> for Population {
@@ -135,3 +642,389 @@ impl<'a> IntoIterator for &'a Population {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bincode::{deserialize, serialize};
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    use fixtures::{params, weighted};
+    use genome::{Genome, GenomeError};
+    use params::ParamsError;
+    use species::Species;
+
+    use super::{
+        allocate_offspring, cull_stagnant, GenerationError, Population,
+    };
+
+    #[test]
+    fn allocate_offspring_proportionally() {
+        assert_eq!(allocate_offspring(&[1.0, 3.0], 8), vec![2, 6]);
+        assert_eq!(allocate_offspring(&[1.0, 1.0, 1.0], 10), vec![4, 3, 3]);
+        assert_eq!(allocate_offspring(&[0.0, 0.0], 5), vec![3, 2]);
+        assert_eq!(
+            allocate_offspring(&[2.0, 1.0, 0.0], 300),
+            vec![200, 100, 0]
+        );
+    }
+
+    #[test]
+    fn cull_stagnant_keeps_protected() {
+        let mut params = params();
+        params.stagnation_limit = 5;
+        params.stagnation_protected = 1;
+
+        let mut species = (0..4)
+            .map(|id| {
+                let mut s = Species::new(id, Genome::new(&params), 0);
+                s.best_fitness = id as f32;
+                s.stagnation = if id == 1 { 0 } else { 10 };
+                (s, ())
+            })
+            .collect::<Vec<_>>();
+        cull_stagnant(&mut species, &params);
+
+        let ids = species.iter().map(|(s, _)| s.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn new_checks_params() {
+        let mut params = params();
+        assert!(Population::new(params).is_ok());
+
+        params.weight_range = 0.0;
+        assert_eq!(
+            Population::new(params).unwrap_err(),
+            ParamsError::NotPositive("weight-range", 0.0)
+        );
+
+        params.weight_range = 1.0;
+        params.gaussian_stddev = -1.0;
+        assert_eq!(
+            Population::new(params).unwrap_err(),
+            ParamsError::Negative("gaussian-stddev", -1.0)
+        );
+    }
+
+    #[test]
+    fn elitism_keeps_best() {
+        let mut params = params();
+        params.elitism_global = 1;
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let fitness = |genome: &Genome| -> Result<f32, ()> {
+            Ok(genome.genes.iter().filter(|g| g.enabled).count() as f32)
+        };
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        let mut best = 0.0;
+        for _ in 0..20 {
+            let gen_best = pop.into_iter()
+                .map(|g| fitness(g).unwrap())
+                .fold(0.0, |a: f32, b| a.max(b));
+            assert!(gen_best >= best);
+            best = gen_best;
+            let (next, stats) = pop.run_generation(&mut rng, fitness).unwrap();
+            assert_eq!(stats.best_fitness, gen_best);
+            assert_eq!(stats.species.iter().map(|s| s.size).sum::<usize>(), 30);
+            pop = next;
+            assert_eq!(pop.len(), 30);
+        }
+        assert!(best > 0.0);
+    }
+
+    #[test]
+    fn stats_count_new_innovations() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let fitness = |genome: &Genome| Ok::<_, ()>(genome.len() as f32);
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        for _ in 0..3 {
+            let (next, stats) = pop.run_generation(&mut rng, fitness).unwrap();
+            assert_eq!(stats.innovations, next.innovations.len());
+            pop = next;
+        }
+
+        params.persist_innovations = true;
+        let mut pop = Population::new(params).unwrap();
+        for _ in 0..3 {
+            let (next, stats) = pop.run_generation(&mut rng, fitness).unwrap();
+            let recorded = next.innovations.len() - pop.innovations.len();
+            assert_eq!(stats.innovations, recorded);
+            pop = next;
+        }
+        assert!(!pop.innovations.is_empty());
+    }
+
+    #[test]
+    fn novelty_fills_archive() {
+        let mut params = params();
+        params.novelty_archive_rate = 1.0;
+        params.population_size = 30;
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        for _ in 0..3 {
+            pop = pop.run_generation_novelty(&mut rng, |genome| {
+                Ok::<_, ()>((0.0, vec![genome.len() as f32]))
+            }).unwrap()
+                .0;
+            assert_eq!(pop.len(), 30);
+        }
+        assert_eq!(pop.archive().len(), 90);
+    }
+
+    #[test]
+    fn novelty_tracks_raw_fitness() {
+        let mut params = params();
+        params.novelty_weight = 1.0;
+        params.population_size = 30;
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let pop = Population::new(params).unwrap();
+        let mut i = 0;
+        let pop = pop.run_generation_novelty(&mut rng, |_| {
+            i += 1;
+            Ok::<_, ()>((0.5, vec![i as f32]))
+        }).unwrap()
+            .0;
+
+        let best = pop.species.iter().map(|s| s.best_fitness);
+        assert_eq!(best.fold(f32::NEG_INFINITY, f32::max), 0.5);
+    }
+
+    #[test]
+    fn multi_objective_keeps_front() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        for _ in 0..5 {
+            pop = pop.run_generation_multi_objective(&mut rng, |genome| {
+                let enabled = genome.genes.iter().filter(|g| g.enabled);
+                let enabled = enabled.count() as f32;
+                Ok::<_, ()>(vec![enabled, -(genome.len() as f32)])
+            }).unwrap()
+                .0;
+            assert_eq!(pop.len(), 30);
+        }
+
+        let front = pop.pareto_front();
+        assert!(!front.is_empty());
+        for (_, a) in front {
+            for (_, b) in front {
+                assert!(!::pareto::dominates(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn multi_objective_tracks_first_objective() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        for _ in 0..3 {
+            pop = pop.run_generation_multi_objective(&mut rng, |genome| {
+                Ok::<_, ()>(vec![0.5, -(genome.len() as f32)])
+            }).unwrap()
+                .0;
+        }
+
+        let best = pop.species.iter().map(|s| s.best_fitness);
+        assert_eq!(best.fold(f32::NEG_INFINITY, f32::max), 0.5);
+    }
+
+    #[test]
+    fn parallel_matches_any_jobs() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let fitness = |genome: &Genome, rng: &mut XorShiftRng| {
+            Ok::<_, ()>(genome.len() as f32 + rng.next_f32())
+        };
+        let run = |jobs| {
+            let mut rng = XorShiftRng::new_unseeded();
+            let mut pop = Population::new(params).unwrap();
+            for _ in 0..5 {
+                pop = pop.run_generation_parallel(&mut rng, jobs, fitness)
+                    .unwrap()
+                    .0;
+            }
+            pop.into_iter().cloned().collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(4));
+
+        let pop = Population::new(params).unwrap();
+        let result = pop.run_generation_parallel(
+            &mut XorShiftRng::new_unseeded(),
+            4,
+            |_: &Genome, _: &mut XorShiftRng| Err::<f32, _>("failed"),
+        );
+        assert_eq!(result.err(), Some(GenerationError::Evaluation("failed")));
+    }
+
+    #[test]
+    fn parallel_stops_after_failure() {
+        let mut params = params();
+        params.population_size = 30;
+
+        let evaluated = AtomicUsize::new(0);
+        let pop = Population::new(params).unwrap();
+        let result = pop.run_generation_parallel(
+            &mut XorShiftRng::new_unseeded(),
+            1,
+            |_: &Genome, _: &mut XorShiftRng| {
+                let i = evaluated.fetch_add(1, Ordering::SeqCst);
+                if i == 2 {
+                    Err(i)
+                } else {
+                    Ok(i as f32)
+                }
+            },
+        );
+        assert_eq!(result.err(), Some(GenerationError::Evaluation(2)));
+        assert_eq!(evaluated.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn evaluations_reproduce_fitness() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let fitness = |genome: &Genome, rng: &mut XorShiftRng| {
+            Ok::<_, ()>(genome.len() as f32 + rng.next_f32())
+        };
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        assert!(pop.champion().is_none());
+        for _ in 0..3 {
+            pop = pop.run_generation_parallel(&mut rng, 2, fitness)
+                .unwrap()
+                .0;
+        }
+
+        assert_eq!(pop.evaluations().len(), 30);
+        for evaluation in pop.evaluations() {
+            let seed = evaluation.seed.unwrap();
+            let mut rng = XorShiftRng::from_seed(seed);
+            let fitness = fitness(&evaluation.genome, &mut rng).unwrap();
+            assert_eq!(evaluation.fitness, fitness);
+        }
+
+        let ranked = pop.ranked_evaluations();
+        assert!(ranked.windows(2).all(|w| w[0].fitness >= w[1].fitness));
+        assert_eq!(pop.champion(), Some(ranked[0]));
+    }
+
+    #[test]
+    fn hall_of_fame_reinjects_lost() {
+        let mut params = params();
+        params.hall_of_fame_elites = 1;
+        params.hall_of_fame_size = 5;
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut pop = Population::new(params).unwrap();
+        for generation in 0..10 {
+            // Favour small genomes early on, so the champion gets lost once
+            // larger ones take over.
+            let fitness = |genome: &Genome| {
+                let len = genome.len() as f32;
+                Ok::<_, ()>(if generation < 3 { 10.0 - len } else { len })
+            };
+            let next = pop.run_generation(&mut rng, fitness).unwrap().0;
+            assert_eq!(next.len(), 30);
+
+            let hall = next.hall_of_fame();
+            assert!(hall.len() <= 5);
+            let best = &hall.entries()[0].genome;
+            assert!(next.into_iter().any(|g| g == best));
+            pop = next;
+        }
+    }
+
+    #[test]
+    fn seeded_runs_reproduce() {
+        let mut params = params();
+        params.mutation_rate = 1.0;
+        params.population_size = 30;
+
+        let run = |file: &[u8]| {
+            let mut pop = deserialize::<Population>(file).unwrap();
+            for _ in 0..5 {
+                let mut rng = pop.rng();
+                pop = pop.run_generation(&mut rng, |genome| {
+                    Ok::<_, ()>(genome.len() as f32)
+                }).unwrap()
+                    .0;
+            }
+            serialize(&pop).unwrap()
+        };
+        let file = |seed| {
+            serialize(&Population::with_seed(params, seed).unwrap()).unwrap()
+        };
+        assert_eq!(run(&file(1)), run(&file(1)));
+        assert_ne!(run(&file(1)), run(&file(2)));
+    }
+
+    #[test]
+    fn strict_mode_accepts_mutations() {
+        for &recurrent in &[false, true] {
+            let mut params = params();
+            params.mutation_rate = 1.0;
+            params.population_size = 30;
+            params.recurrent = recurrent;
+            params.strict = true;
+
+            let mut rng = XorShiftRng::new_unseeded();
+            let mut pop = Population::new(params).unwrap();
+            for _ in 0..20 {
+                pop = pop.run_generation(&mut rng, |genome| {
+                    Ok::<_, ()>(genome.len() as f32)
+                }).unwrap()
+                    .0;
+            }
+        }
+    }
+
+    #[test]
+    fn strict_mode_reports_crossover() {
+        let mut params = params();
+        params.mutation_rate = 0.0;
+        params.population_size = 30;
+        params.strict = true;
+
+        let mut genome = Genome::new(&params);
+        genome.genes.push(weighted(1, 0, f32::NAN, 1));
+        let mut pop = Population::new(params).unwrap();
+        pop.species[0].members = vec![genome; 30];
+
+        let result = pop.run_generation(&mut XorShiftRng::new_unseeded(), |_| {
+            Ok::<_, ()>(1.0)
+        });
+        assert_eq!(
+            result.err(),
+            Some(GenerationError::InvalidGenome(
+                None,
+                GenomeError::NanWeight(1)
+            ))
+        );
+    }
+}
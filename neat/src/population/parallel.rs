@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crossbeam;
+use rand::{SeedableRng, XorShiftRng};
+
+use genome::Genome;
+
+/// Evaluates the given genomes on `jobs` threads. Each genome gets its own
+/// RNG, seeded with the corresponding seed, so the results do not depend on
+/// the number of threads or the order the genomes are evaluated in.
+///
+/// Genomes are handed out in order, and once any evaluation fails, no more
+/// are started. The error from the first failing genome is returned.
+pub fn evaluate<E, F>(
+    genomes: &[&Genome],
+    seeds: &[[u32; 4]],
+    jobs: usize,
+    fitness: &F,
+) -> Result<Vec<f32>, E>
+where
+    E: Send,
+    F: Fn(&Genome, &mut XorShiftRng) -> Result<f32, E> + Sync,
+{
+    assert_eq!(genomes.len(), seeds.len());
+    let n = genomes.len();
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+
+    let mut results = crossbeam::scope(|scope| {
+        let threads = (0..jobs.max(1).min(n.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    while !failed.load(Ordering::SeqCst) {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= n {
+                            break;
+                        }
+                        let mut rng = XorShiftRng::from_seed(seeds[i]);
+                        let result = fitness(genomes[i], &mut rng);
+                        if result.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        results.push((i, result));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .flat_map(|thread| thread.join())
+            .collect::<Vec<_>>()
+    });
+
+    // Every genome before a failing one was started before the failure, so
+    // they have all been evaluated.
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,160 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use float_ord::FloatOrd;
+
+/// Statistics about a generation, as returned by `Population::run_generation`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GenerationStats {
+    /// The number of the generation that was evaluated.
+    pub generation: usize,
+
+    /// The highest fitness in the generation.
+    pub best_fitness: f32,
+
+    /// The mean fitness of the generation.
+    pub mean_fitness: f32,
+
+    /// The median fitness of the generation.
+    pub median_fitness: f32,
+
+    /// The lowest fitness in the generation.
+    pub worst_fitness: f32,
+
+    /// Statistics about each species in the generation.
+    pub species: Vec<SpeciesStats>,
+
+    /// The mean number of genes in each genome.
+    pub mean_genome_length: f32,
+
+    /// The mean number of hidden nodes in each genome.
+    pub mean_hidden_nodes: f32,
+
+    /// The number of new structural innovations made while producing the next
+    /// generation. These are add-connection and add-node mutations that were
+    /// not already in the innovation registry, so identical mutations made
+    /// by several offspring count once.
+    pub innovations: usize,
+}
+
+/// Statistics about a single species in a generation.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SpeciesStats {
+    /// The ID of the species.
+    pub id: usize,
+
+    /// The number of members of the species.
+    pub size: usize,
+
+    /// The highest fitness in the species.
+    pub best_fitness: f32,
+}
+
+impl GenerationStats {
+    /// Computes the fitness statistics for a generation. The species are
+    /// given by their IDs and the fitnesses of their members.
+    pub fn new(
+        generation: usize,
+        species: &[(usize, &[f32])],
+        mean_genome_length: f32,
+        mean_hidden_nodes: f32,
+        innovations: usize,
+    ) -> GenerationStats {
+        let mut all = species
+            .iter()
+            .flat_map(|&(_, fitnesses)| fitnesses.iter().cloned())
+            .collect::<Vec<_>>();
+        all.sort_by_key(|&f| FloatOrd(f));
+
+        let median = if all.is_empty() {
+            0.0
+        } else if all.len() % 2 == 0 {
+            (all[all.len() / 2 - 1] + all[all.len() / 2]) / 2.0
+        } else {
+            all[all.len() / 2]
+        };
+
+        GenerationStats {
+            generation,
+            best_fitness: all.last().cloned().unwrap_or(0.0),
+            mean_fitness: mean(&all),
+            median_fitness: median,
+            worst_fitness: all.first().cloned().unwrap_or(0.0),
+            species: species
+                .iter()
+                .map(|&(id, fitnesses)| SpeciesStats {
+                    id,
+                    size: fitnesses.len(),
+                    best_fitness: fitnesses
+                        .iter()
+                        .cloned()
+                        .max_by_key(|&f| FloatOrd(f))
+                        .unwrap_or(0.0),
+                })
+                .collect(),
+            mean_genome_length,
+            mean_hidden_nodes,
+            innovations,
+        }
+    }
+}
+
+impl Display for GenerationStats {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(
+            fmt,
+            "generation {}: fitness best {}, mean {}, median {}, worst {}; \
+             {} species; mean length {}, mean hidden nodes {}; \
+             {} innovations",
+            self.generation,
+            self.best_fitness,
+            self.mean_fitness,
+            self.median_fitness,
+            self.worst_fitness,
+            self.species.len(),
+            self.mean_genome_length,
+            self.mean_hidden_nodes,
+            self.innovations
+        )
+    }
+}
+
+/// Returns the mean of the given values, or zero if there are none.
+pub fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GenerationStats, SpeciesStats};
+
+    #[test]
+    fn fitness_stats() {
+        let stats = GenerationStats::new(
+            3,
+            &[(1, &[1.0, 5.0]), (4, &[2.0, 0.0]), (5, &[])],
+            2.0,
+            0.5,
+            6,
+        );
+        assert_eq!(stats.best_fitness, 5.0);
+        assert_eq!(stats.mean_fitness, 2.0);
+        assert_eq!(stats.median_fitness, 1.5);
+        assert_eq!(stats.worst_fitness, 0.0);
+        assert_eq!(
+            stats.species[1],
+            SpeciesStats {
+                id: 4,
+                size: 2,
+                best_fitness: 2.0,
+            }
+        );
+
+        let stats =
+            GenerationStats::new(0, &[(0, &[3.0, 1.0, 2.0])], 0.0, 0.0, 0);
+        assert_eq!(stats.median_fitness, 2.0);
+    }
+}
@@ -1,27 +1,144 @@
 mod iter;
 
+use std::cmp::{max, Reverse};
+use std::f32;
+use std::ops::{Index, IndexMut};
+
+use float_ord::FloatOrd;
+use rand::Rng;
+
+use crossover::crossover;
 use genome::Genome;
+use params::Params;
 
 pub use self::iter::SpeciesIter;
 
-/// A single species. Just a newtype.
-#[derive(Clone, Debug, Deserialize, From, Index, IndexMut, Into, Serialize)]
-pub struct Species(pub Vec<Genome>);
+/// A single species, which persists across generations.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Species {
+    /// The unique ID of the species within its population.
+    pub id: usize,
+
+    /// The genome new organisms are compared against to decide whether they
+    /// belong to the species.
+    pub representative: Genome,
+
+    /// The generation the species was created in.
+    pub created: usize,
+
+    /// The best fitness any member of the species has ever had, or negative
+    /// infinity if its members have not been evaluated yet.
+    pub best_fitness: f32,
+
+    /// The number of generations since `best_fitness` last improved.
+    pub stagnation: usize,
+
+    /// The members of the species.
+    pub members: Vec<Genome>,
+}
 
 impl Species {
-    /// Creates a new, empty species.
-    pub fn empty() -> Species {
-        Species(Vec::new())
+    /// Creates a new, empty species, represented by the given genome.
+    pub fn new(id: usize, representative: Genome, created: usize) -> Species {
+        Species {
+            id,
+            representative,
+            created,
+            best_fitness: f32::NEG_INFINITY,
+            stagnation: 0,
+            members: Vec::new(),
+        }
     }
 
     /// Returns the number of individuals in the species.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.members.len()
     }
 
     /// Creates a new species with a default genome and the given size.
-    pub fn with_size(population_size: usize) -> Species {
-        Species(vec![Genome::new(); population_size])
+    pub fn with_size(id: usize, params: &Params) -> Species {
+        let genome = Genome::new(params);
+        let mut species = Species::new(id, genome.clone(), 0);
+        species.members = vec![genome; params.population_size];
+        species
+    }
+
+    /// Returns the index of the fittest member of the species.
+    ///
+    /// `fitnesses` must have the same length as the species, and the species
+    /// must not be empty.
+    pub fn champion(&self, fitnesses: &[f32]) -> usize {
+        (0..self.len())
+            .max_by_key(|&i| FloatOrd(fitnesses[i]))
+            .unwrap()
+    }
+
+    /// Records the fitnesses of the members of the species. The fittest member
+    /// becomes the representative, and the stagnation counter is reset if it
+    /// beats the best fitness seen so far.
+    ///
+    /// `fitnesses` must have the same length as the species, and the species
+    /// must not be empty.
+    pub fn update_fitness(&mut self, fitnesses: &[f32]) {
+        let champion = self.champion(fitnesses);
+        let best = fitnesses[champion];
+
+        self.representative = self.members[champion].clone();
+        if best > self.best_fitness {
+            self.best_fitness = best;
+            self.stagnation = 0;
+        } else {
+            self.stagnation += 1;
+        }
+    }
+
+    /// Produces `n` offspring from the species. Only the fittest members of
+    /// the species, as given by `params.survival_threshold`, become parents.
+    ///
+    /// `fitnesses` must have the same length as the species, and the species
+    /// must not be empty.
+    pub fn reproduce<R: Rng>(
+        &self,
+        rng: &mut R,
+        fitnesses: &[f32],
+        n: usize,
+        params: &Params,
+    ) -> Vec<Genome> {
+        let mut parents = self.members
+            .iter()
+            .zip(fitnesses.iter().cloned())
+            .collect::<Vec<_>>();
+        parents.sort_by_key(|&(_, fitness)| Reverse(FloatOrd(fitness)));
+
+        let survivors =
+            (parents.len() as f32 * params.survival_threshold).ceil();
+        parents.truncate(max(survivors as usize, 1));
+
+        (0..n)
+            .map(|_| {
+                let p1 = *rng.choose(&parents).unwrap();
+                if rng.next_f32() < params.crossover_rate {
+                    let p2 = *rng.choose(&parents).unwrap();
+                    crossover(rng, p1, p2, params)
+                } else {
+                    p1.0.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Index<usize> for Species {
+    type Output = Genome;
+
+    fn index(&self, n: usize) -> &Genome {
+        &self.members[n]
+    }
+}
+
+impl IndexMut<usize> for Species {
+    fn index_mut(&mut self, n: usize) -> &mut Genome {
+        &mut self.members[n]
     }
 }
 
@@ -36,3 +153,29 @@ impl<'a> IntoIterator for &'a Species {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fixtures::params;
+    use genome::Genome;
+
+    use super::Species;
+
+    #[test]
+    fn negative_fitness_improves() {
+        let mut params = params();
+        params.population_size = 2;
+        let mut species = Species::with_size(0, &params);
+        species.update_fitness(&[-3.0, -4.0]);
+        assert_eq!(species.best_fitness, -3.0);
+        assert_eq!(species.stagnation, 0);
+
+        species.update_fitness(&[-5.0, -6.0]);
+        assert_eq!(species.stagnation, 1);
+
+        species.members[1] = Genome::new(&params);
+        species.update_fitness(&[-7.0, -1.0]);
+        assert_eq!(species.best_fitness, -1.0);
+        assert_eq!(species.stagnation, 0);
+    }
+}
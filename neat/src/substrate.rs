@@ -0,0 +1,341 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+
+use activation::Activation;
+use genome::{Gene, Genome, NodeGene};
+use network::Network;
+use params::Params;
+
+/// The fixed geometry of a network whose weights are generated by a CPPN, as
+/// in HyperNEAT.
+///
+/// The substrate is a stack of layers of neurons, each placed at a point in
+/// the plane. The first layer holds the inputs and the last the outputs, and
+/// every neuron is connected to every neuron in the next layer. The weight of
+/// each connection comes from querying the CPPN with the coordinates of its
+/// endpoints.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Substrate {
+    /// The coordinates of the neurons in each layer.
+    pub layers: Vec<Vec<(f32, f32)>>,
+
+    /// The activation function of the hidden and output neurons.
+    #[serde(with = "::util::tofromstr")]
+    pub activation: Activation,
+
+    /// The magnitude the CPPN's output must exceed for a connection to be
+    /// expressed. It must be at least zero and less than one.
+    #[serde(deserialize_with = "deserialize_threshold")]
+    pub threshold: f32,
+
+    /// The magnitude of the weight of a connection for which the CPPN's
+    /// output is at least one.
+    pub max_weight: f32,
+}
+
+impl Substrate {
+    /// Creates a substrate with the given layers, which must include at least
+    /// the input and output layers.
+    pub fn new(layers: Vec<Vec<(f32, f32)>>) -> Substrate {
+        assert!(layers.len() >= 2, "A substrate needs at least two layers");
+        Substrate {
+            layers,
+            activation: Activation::Sigmoid,
+            threshold: 0.2,
+            max_weight: 3.0,
+        }
+    }
+
+    /// Returns the coordinates of a grid of neurons, such as a rasterized
+    /// view of the world, spread evenly over the square from (-1, -1) to
+    /// (1, 1). The neurons are in row-major order.
+    pub fn grid(width: usize, height: usize) -> Vec<(f32, f32)> {
+        let coord = |i: usize, n: usize| {
+            if n == 1 {
+                0.0
+            } else {
+                2.0 * i as f32 / (n - 1) as f32 - 1.0
+            }
+        };
+        (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| (coord(x, width), coord(y, height)))
+            })
+            .collect()
+    }
+
+    /// Returns the number of inputs to the substrate's networks.
+    pub fn inputs(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    /// Returns the number of outputs from the substrate's networks.
+    pub fn outputs(&self) -> usize {
+        self.layers[self.layers.len() - 1].len()
+    }
+
+    /// Builds the network described by the substrate, with weights from the
+    /// given CPPN. The CPPN must take four inputs, the coordinates
+    /// (x1, y1, x2, y2) of a connection's endpoints, and its first output is
+    /// the connection's weight.
+    ///
+    /// The given parameters are those the CPPN was evolved with. Both the
+    /// CPPN and the network are always feed-forward, so that every query of
+    /// the CPPN is independent of the ones before it.
+    pub fn build_network(
+        &self,
+        cppn: &Genome,
+        params: &Params,
+    ) -> Result<Network, SubstrateError> {
+        if params.inputs != 4 {
+            return Err(SubstrateError::CppnInputs(params.inputs));
+        }
+        check_threshold(self.threshold)?;
+        let mut cppn = {
+            let mut params = *params;
+            params.recurrent = false;
+            cppn.build_network(&params)
+        };
+
+        let mut substrate = *params;
+        substrate.inputs = self.inputs();
+        substrate.outputs = self.outputs();
+        substrate.recurrent = false;
+        substrate.activation = self.activation;
+        substrate.output_activation = None;
+
+        let ids = self.node_ids(&substrate);
+        let mut genome = Genome::new(&substrate);
+        for l in 0..self.layers.len() - 1 {
+            for (i, &(x1, y1)) in self.layers[l].iter().enumerate() {
+                for (j, &(x2, y2)) in self.layers[l + 1].iter().enumerate() {
+                    let out = cppn.calculate(&[x1, y1, x2, y2])[0];
+                    if let Some(weight) = self.express(out) {
+                        let innovation = genome.genes.len() + 1;
+                        genome.genes.push(Gene {
+                            from: ids[l][i],
+                            to: ids[l + 1][j],
+                            enabled: true,
+                            weight,
+                            innovation,
+                        });
+                    }
+                }
+            }
+        }
+        for layer in &ids[1..ids.len() - 1] {
+            genome.nodes.extend(
+                layer.iter().map(|&n| NodeGene::new(n, self.activation)),
+            );
+        }
+
+        Ok(genome.build_network(&substrate))
+    }
+
+    /// Returns the node ID of each neuron in each layer, following the layout
+    /// described on `Genome`.
+    fn node_ids(&self, params: &Params) -> Vec<Vec<usize>> {
+        let mut next = params.first_hidden();
+        let last = self.layers.len() - 1;
+        self.layers
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                if l == 0 {
+                    (params.outputs..params.first_hidden()).collect()
+                } else if l == last {
+                    (0..params.outputs).collect()
+                } else {
+                    next += layer.len();
+                    (next - layer.len()..next).collect()
+                }
+            })
+            .collect()
+    }
+
+    /// Converts an output of the CPPN to a connection weight, or `None` if
+    /// the connection is not expressed.
+    fn express(&self, out: f32) -> Option<f32> {
+        let magnitude = out.abs();
+        if magnitude <= self.threshold {
+            return None;
+        }
+
+        let scaled = (magnitude - self.threshold) / (1.0 - self.threshold);
+        Some(out.signum() * scaled.min(1.0) * self.max_weight)
+    }
+}
+
+/// Checks that a threshold is at least zero and less than one.
+fn check_threshold(threshold: f32) -> Result<f32, SubstrateError> {
+    if 0.0 <= threshold && threshold < 1.0 {
+        Ok(threshold)
+    } else {
+        Err(SubstrateError::Threshold(threshold))
+    }
+}
+
+/// Deserializes a threshold, rejecting any that `check_threshold` rejects.
+fn deserialize_threshold<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    check_threshold(f32::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+/// An error building a network from a substrate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubstrateError {
+    /// The CPPN has the given number of inputs, rather than four.
+    CppnInputs(usize),
+
+    /// The substrate's threshold is not at least zero and less than one.
+    Threshold(f32),
+}
+
+impl Display for SubstrateError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            SubstrateError::CppnInputs(n) => {
+                write!(fmt, "a CPPN needs 4 inputs, not {}", n)
+            }
+            SubstrateError::Threshold(t) => write!(
+                fmt,
+                "a substrate's threshold must be in [0, 1), not {}",
+                t
+            ),
+        }
+    }
+}
+
+impl Error for SubstrateError {
+    fn description(&self) -> &str {
+        "invalid substrate"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use fixtures::{gene, params, weighted};
+    use genome::Genome;
+
+    use super::{Substrate, SubstrateError};
+
+    #[test]
+    fn grid_spans_square() {
+        assert_eq!(
+            Substrate::grid(3, 2),
+            vec![
+                (-1.0, -1.0),
+                (0.0, -1.0),
+                (1.0, -1.0),
+                (-1.0, 1.0),
+                (0.0, 1.0),
+                (1.0, 1.0),
+            ]
+        );
+        assert_eq!(Substrate::grid(1, 1), vec![(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn weights_follow_cppn() {
+        let params = params();
+
+        // The weight is the sigmoid of x1, so the left input is inhibitory
+        // and the right input excitatory.
+        let mut cppn = Genome::new(&params);
+        cppn.genes.push(gene(1, 0, 1));
+
+        let substrate = Substrate::new(vec![
+            vec![(-1.0, 0.0), (1.0, 0.0)],
+            vec![(0.0, 1.0)],
+        ]);
+        let mut network = substrate.build_network(&cppn, &params).unwrap();
+        assert_eq!(network.inputs(), 2);
+        assert_eq!(network.outputs(), 1);
+
+        let left = network.calculate(&[1.0, 0.0])[0];
+        let right = network.calculate(&[0.0, 1.0])[0];
+        assert!(left < right);
+        assert!(network.calculate(&[1.0, 1.0])[0].abs() < 1e-5);
+    }
+
+    #[test]
+    fn recurrent_cppn_is_queried_independently() {
+        let mut params = params();
+        params.recurrent = true;
+
+        // The output feeds back into itself, so a recurrent CPPN would give
+        // a different weight for every query after the first.
+        let mut cppn = Genome::new(&params);
+        cppn.genes = vec![weighted(1, 0, 1.0, 1), weighted(0, 0, 2.0, 2)];
+
+        let substrate = Substrate::new(vec![
+            vec![(1.0, 0.0), (1.0, 0.0)],
+            vec![(0.0, 1.0)],
+        ]);
+        let mut network = substrate.build_network(&cppn, &params).unwrap();
+        let left = network.calculate(&[1.0, 0.0])[0];
+        let right = network.calculate(&[0.0, 1.0])[0];
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn threshold_scales_weights() {
+        let mut substrate = Substrate::new(vec![vec![(0.0, 0.0)]; 2]);
+        substrate.threshold = 0.5;
+        assert_eq!(substrate.express(0.5), None);
+        assert_eq!(substrate.express(-0.75), Some(-1.5));
+        assert_eq!(substrate.express(2.0), Some(3.0));
+
+        substrate.threshold = 0.0;
+        assert_eq!(substrate.express(0.0), None);
+        assert_eq!(substrate.express(0.5), Some(1.5));
+    }
+
+    #[test]
+    fn invalid_substrates_are_rejected() {
+        let mut params = params();
+        let cppn = Genome::new(&params);
+        let mut substrate = Substrate::new(vec![vec![(0.0, 0.0)]; 2]);
+        substrate.threshold = 1.0;
+        assert_eq!(
+            substrate.build_network(&cppn, &params).err(),
+            Some(SubstrateError::Threshold(1.0))
+        );
+
+        substrate.threshold = 0.5;
+        params.inputs = 3;
+        assert_eq!(
+            substrate.build_network(&cppn, &params).err(),
+            Some(SubstrateError::CppnInputs(3))
+        );
+
+        let json = serde_json::to_string(&substrate).unwrap();
+        assert!(serde_json::from_str::<Substrate>(&json).is_ok());
+        let json = json.replace("0.5", "-0.5");
+        assert!(serde_json::from_str::<Substrate>(&json).is_err());
+    }
+
+    #[test]
+    fn hidden_layers_follow_io() {
+        let mut params = params();
+        params.inputs = 2;
+        params.outputs = 1;
+
+        let substrate = Substrate::new(vec![
+            Substrate::grid(2, 1),
+            Substrate::grid(3, 1),
+            Substrate::grid(2, 1),
+            Substrate::grid(1, 1),
+        ]);
+        assert_eq!(
+            substrate.node_ids(&params),
+            vec![vec![1, 2], vec![3, 4, 5], vec![6, 7], vec![0]]
+        );
+    }
+}
@@ -48,6 +48,45 @@ pub mod tofromstr {
     }
 }
 
+/// Serializer/deserializer functions that use `ToString`/`FromStr` on optional
+/// values.
+pub mod tofromstr_option {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::de::{Deserialize, Deserializer, Error as DeError};
+    use serde::ser::{Serialize, Serializer};
+
+    /// Deserializes using `FromStr`.
+    pub fn deserialize<'de, D, E, T>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        E: Display,
+        T: FromStr<Err = E>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => s.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    /// Serializes using `ToString`.
+    pub fn serialize<S, T>(
+        t: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: ToString,
+    {
+        t.as_ref()
+            .map(|t| t.to_string())
+            .serialize(serializer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::{from_slice, to_value, to_vec, Value};
@@ -37,9 +37,15 @@ fn main() {
         .expect("Failed to start logger");
 
     let result = match options.subcommand {
+        #[cfg(feature = "train")]
+        Subcommand::Dot(dot) => dot.run(),
+
         #[cfg(feature = "train")]
         Subcommand::Extract(extract) => extract.run(),
 
+        #[cfg(feature = "train")]
+        Subcommand::HallOfFame(hall_of_fame) => hall_of_fame.run(),
+
         #[cfg(feature = "train")]
         Subcommand::List(list) => list.run(),
 
@@ -80,11 +86,25 @@ struct Options {
 
 #[derive(Debug, StructOpt)]
 enum Subcommand {
+    /// Draws a single neural net as a Graphviz graph.
+    #[cfg(feature = "train")]
+    #[structopt(name = "dot")]
+    Dot(train::DotOptions),
+
     /// Extracts a single neural net from a generation file.
     #[cfg(feature = "train")]
-    #[structopt(name = "extract")]
+    #[structopt(
+        name = "extract",
+        raw(setting = "structopt::clap::AppSettings::AllowMissingPositional")
+    )]
     Extract(train::ExtractOptions),
 
+    /// Lists the neural nets in a generation file's hall of fame, or extracts
+    /// one of them.
+    #[cfg(feature = "train")]
+    #[structopt(name = "hall-of-fame")]
+    HallOfFame(train::HallOfFameOptions),
+
     /// Lists the neural nets in a generation file.
     #[cfg(feature = "train")]
     #[structopt(name = "list")]
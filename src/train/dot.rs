@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use bincode::deserialize_from;
+use failure::Error;
+use inflector::numbers::ordinalize::ordinalize;
+use neuroflap_neat::{Genome, Params, Population};
+use structopt::StructOpt;
+
+use super::INPUT_LABELS;
+
+/// Options taken by the `dot` subcommand.
+#[derive(Debug, StructOpt)]
+pub struct Options {
+    /// The input genome file, or generation file if `--index` is given.
+    pub input_file: PathBuf,
+
+    /// The output DOT file.
+    pub dot_file: PathBuf,
+
+    /// The index of the entry to draw from a generation file.
+    #[structopt(long = "index", short = "n")]
+    pub index: Option<usize>,
+}
+
+impl Options {
+    /// Runs dot mode.
+    pub fn run(self) -> Result<(), Error> {
+        let f = File::open(self.input_file)?;
+        let (genome, params) = if let Some(n) = self.index {
+            let pop: Population = deserialize_from(f)?;
+            if pop.len() <= n {
+                let nth = ordinalize(&format!("{}", n));
+                bail!("There isn't a {} genome", nth)
+            }
+            (pop[n].clone(), pop.params)
+        } else {
+            // Genome files don't store their parameters, but they're only
+            // useful with the game's layout, which is the default.
+            let genome: Genome = deserialize_from(f)?;
+            let params = Params::from_clap(
+                &Params::clap().get_matches_from(vec!["neuroflap"]),
+            );
+            (genome, params)
+        };
+
+        let mut f = File::create(self.dot_file)?;
+        f.write_all(genome.to_dot(&params, &INPUT_LABELS).as_bytes())?;
+        Ok(())
+    }
+}
@@ -13,7 +13,13 @@ pub struct Options {
     pub generation_file: PathBuf,
 
     /// The index of the entry to extract.
-    pub n: usize,
+    #[structopt(required_unless = "best")]
+    pub n: Option<usize>,
+
+    /// Extracts the fittest neural net of the previous generation, rather
+    /// than the one at an index.
+    #[structopt(long = "best", conflicts_with = "n")]
+    pub best: bool,
 
     /// The output genome file.
     pub genome_file: PathBuf,
@@ -27,12 +33,20 @@ impl Options {
             deserialize_from(f)?
         };
 
-        let genome = if pop.len() <= self.n {
-            let n = format!("{}", self.n);
-            let nth = ordinalize(&n);
-            bail!("There isn't a {} species", nth)
+        let genome = if self.best {
+            match pop.champion() {
+                Some(evaluation) => &evaluation.genome,
+                None => bail!("The generation file has no evaluated genomes"),
+            }
         } else {
-            &pop[self.n]
+            // clap requires an index unless --best is given.
+            let n = self.n.unwrap();
+            if pop.len() <= n {
+                let nth = ordinalize(&format!("{}", n));
+                bail!("There isn't a {} species", nth)
+            } else {
+                &pop[n]
+            }
         };
 
         let f = File::create(self.genome_file)?;
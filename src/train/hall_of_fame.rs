@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use bincode::{deserialize_from, serialize_into};
+use failure::Error;
+use inflector::numbers::ordinalize::ordinalize;
+use neuroflap_neat::Population;
+
+/// Options taken by the `hall-of-fame` subcommand.
+#[derive(Debug, StructOpt)]
+pub struct Options {
+    /// The generation file.
+    pub generation_file: PathBuf,
+
+    /// The index of the entry to extract. If not present, the hall of fame is
+    /// listed instead.
+    #[structopt(requires = "genome_file")]
+    pub n: Option<usize>,
+
+    /// The output genome file.
+    pub genome_file: Option<PathBuf>,
+}
+
+impl Options {
+    /// Runs hall of fame mode.
+    pub fn run(self) -> Result<(), Error> {
+        let pop: Population = {
+            let f = File::open(self.generation_file)?;
+            deserialize_from(f)?
+        };
+        let entries = pop.hall_of_fame().entries();
+
+        let (n, genome_file) = match (self.n, self.genome_file) {
+            (Some(n), Some(genome_file)) => (n, genome_file),
+            _ => {
+                for (n, entry) in entries.iter().enumerate() {
+                    println!(
+                        "# genome {}: fitness {}, generation {}",
+                        n, entry.fitness, entry.generation
+                    );
+                    print!("{}", entry.genome);
+                }
+                return Ok(());
+            }
+        };
+
+        let genome = if entries.len() <= n {
+            let nth = ordinalize(&format!("{}", n));
+            bail!("There isn't a {} genome in the hall of fame", nth)
+        } else {
+            &entries[n].genome
+        };
+
+        let f = File::create(genome_file)?;
+        serialize_into(f, &genome).map_err(Error::from)
+    }
+}
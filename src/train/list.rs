@@ -10,6 +10,16 @@ use neuroflap_neat::Population;
 pub struct Options {
     /// The generation file.
     pub generation_file: PathBuf,
+
+    /// Lists the Pareto front of the previous generation, with the objectives
+    /// of each neural net, rather than the current generation.
+    #[structopt(long = "pareto", conflicts_with = "evaluated")]
+    pub pareto: bool,
+
+    /// Lists the previous generation, fittest first, with the fitness and
+    /// evaluation seed of each neural net, rather than the current generation.
+    #[structopt(long = "evaluated")]
+    pub evaluated: bool,
 }
 
 impl Options {
@@ -21,8 +31,27 @@ impl Options {
         };
 
         println!("params: {}", pop.params);
-        for (n, genome) in pop.into_iter().enumerate() {
-            println!("{}: {}", n, genome);
+        if self.pareto {
+            let front = pop.pareto_front();
+            for (n, (genome, objectives)) in front.iter().enumerate() {
+                println!("# genome {}: objectives {:?}", n, objectives);
+                print!("{}", genome);
+            }
+        } else if self.evaluated {
+            let ranked = pop.ranked_evaluations();
+            for (n, evaluation) in ranked.into_iter().enumerate() {
+                print!("# genome {}: fitness {}", n, evaluation.fitness);
+                if let Some(seed) = evaluation.seed {
+                    print!(", seed {:?}", seed);
+                }
+                println!();
+                print!("{}", evaluation.genome);
+            }
+        } else {
+            for (n, genome) in pop.into_iter().enumerate() {
+                println!("# genome {}", n);
+                print!("{}", genome);
+            }
         }
 
         Ok(())
@@ -1,9 +1,17 @@
+mod dot;
 mod extract;
+mod hall_of_fame;
 mod list;
 mod new;
 mod train;
 
+pub use self::dot::Options as DotOptions;
 pub use self::extract::Options as ExtractOptions;
+pub use self::hall_of_fame::Options as HallOfFameOptions;
 pub use self::list::Options as ListOptions;
 pub use self::new::Options as NewOptions;
 pub use self::train::Options as TrainOptions;
+
+/// The labels of the inputs the game gives each network, in order.
+pub const INPUT_LABELS: [&str; 4] =
+    ["position", "pipe x", "pipe y", "velocity"];
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use bincode::serialize_into;
 use failure::Error;
 use neuroflap_neat::{Params, Population};
+use rand::{thread_rng, Rng};
 use structopt::StructOpt;
 use structopt::clap::{App, Arg, ArgMatches};
 
@@ -15,18 +16,36 @@ pub struct Options {
 
     /// The training parameters to put in the file.
     pub params: Params,
+
+    /// The seed for the population's RNG. If not present, a random seed is
+    /// chosen.
+    pub seed: Option<u64>,
 }
 
 #[doc(hidden)]
 impl Options {
     pub fn augment_clap<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        Params::augment_clap(app).arg(
-            Arg::with_name("generation_file")
-                .takes_value(true)
-                .multiple(false)
-                .required(true)
-                .help("The generation file to write."),
-        )
+        Params::augment_clap(app)
+            .arg(
+                Arg::with_name("generation_file")
+                    .takes_value(true)
+                    .multiple(false)
+                    .required(true)
+                    .help("The generation file to write."),
+            )
+            .arg(
+                Arg::with_name("seed")
+                    .long("seed")
+                    .takes_value(true)
+                    .multiple(false)
+                    .validator(|s| {
+                        s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string())
+                    })
+                    .help(
+                        "The seed for the population's RNG. If not present, a \
+                         random seed is chosen.",
+                    ),
+            )
     }
 
     pub fn is_subcommand() -> bool {
@@ -44,9 +63,11 @@ impl StructOpt for Options {
             .value_of_os("generation_file")
             .map(PathBuf::from)
             .unwrap();
+        let seed = matches.value_of("seed").map(|s| s.parse().unwrap());
         Options {
             generation_file,
             params: Params::from_clap(matches),
+            seed,
         }
     }
 }
@@ -54,7 +75,9 @@ impl StructOpt for Options {
 impl Options {
     /// Creates a new generation file.
     pub fn run(self) -> Result<(), Error> {
-        let pop = Population::new(self.params);
+        let seed = self.seed.unwrap_or_else(|| thread_rng().gen());
+        info!("Using seed {}", seed);
+        let pop = Population::with_seed(self.params, seed)?;
 
         let f = File::create(self.generation_file)?;
         serialize_into(f, &pop).map_err(Error::from)
@@ -8,9 +8,9 @@ use bincode::{deserialize_from, serialize_into};
 use failure::Error;
 use futures::{Async, stream::poll_fn};
 use inflector::numbers::ordinalize::ordinalize;
-use neuroflap_neat::Population;
+use neuroflap_neat::{GenerationError, Genome, Params, Population};
 use neuroflap_world::{run_one, Event};
-use rand::XorShiftRng;
+use rand::Rng;
 
 use util::SharedRng;
 
@@ -24,6 +24,21 @@ pub struct Options {
     /// generation snapshots will not be made.
     #[structopt(long = "results")]
     pub results_dir: Option<PathBuf>,
+
+    /// Selects by novelty rather than fitness, characterizing each network's
+    /// behaviour by the player's trajectory. Fitness still counts as much as
+    /// the generation's parameters say.
+    #[structopt(long = "novelty")]
+    pub novelty: bool,
+
+    /// Selects by the Pareto front of survival time, network size and number
+    /// of flaps, preferring small networks that flap rarely.
+    #[structopt(long = "multi-objective")]
+    pub multi_objective: bool,
+
+    /// The number of threads to play games on.
+    #[structopt(default_value = "1", long = "jobs", short = "j")]
+    pub jobs: usize,
 }
 
 impl Options {
@@ -33,55 +48,62 @@ impl Options {
             let f = File::open(&self.generation_file)?;
             deserialize_from(f)?
         };
+        pop.params.check()?;
 
-        let mut rng = SharedRng::new(XorShiftRng::new_unseeded());
+        if self.novelty && self.multi_objective {
+            bail!("Novelty search can't be used with multiple objectives");
+        }
+        if self.jobs != 1 && (self.novelty || self.multi_objective) {
+            bail!("Only plain fitness can be evaluated on multiple threads");
+        }
+        if pop.params.inputs != 4 || pop.params.outputs != 1 {
+            bail!(
+                "The game needs networks with 4 inputs and 1 output, not {} \
+                 inputs and {} outputs",
+                pop.params.inputs,
+                pop.params.outputs
+            );
+        }
 
         loop {
             info!("Training generation {}...", pop.generation());
-            pop = pop.run_generation(&mut rng.clone(), |genome| {
-                let network = genome.build_network(pop.params.activation);
-                let (send, recv) = channel();
-                run_one(
-                    poll_fn(|| -> Result<_, !> {
-                        match recv.try_recv() {
-                            Ok(x) => Ok(Async::Ready(Some(x))),
-                            Err(TryRecvError::Empty) => Ok(Async::NotReady),
-                            Err(TryRecvError::Disconnected) => {
-                                Ok(Async::Ready(None))
-                            }
-                        }
-                    }),
-                    |world| {
-                        let (next_pipe_x, next_pipe_y) = world
-                            .pipes
-                            .iter()
-                            .cloned()
-                            .filter(|&(x, _)| x >= 0.5)
-                            .next()
-                            .unwrap_or((0.0, 0.0));
-                        debug!("{} {}", next_pipe_x, next_pipe_y);
-
-                        let out = network.calculate([
-                            world.position,
-                            next_pipe_x,
-                            next_pipe_y,
-                            world.velocity,
-                        ]);
-
-                        if out > 0.5 {
-                            send.send(Event::Jump)?;
-                        }
-                        Ok(())
+            let mut rng = SharedRng::new(pop.rng());
+            let (next, stats) = if self.novelty {
+                pop.run_generation_novelty(&mut rng.clone(), |genome| {
+                    let outcome = evaluate(genome, &pop.params, &mut rng)?;
+                    Ok::<_, Error>((outcome.survived, outcome.trajectory))
+                }).map_err(generation_error)?
+            } else if self.multi_objective {
+                pop.run_generation_multi_objective(
+                    &mut rng.clone(),
+                    |genome| {
+                        let outcome = evaluate(genome, &pop.params, &mut rng)?;
+                        let size = genome.genes.iter().filter(|g| g.enabled);
+                        Ok::<_, Error>(vec![
+                            outcome.survived,
+                            -(size.count() as f32),
+                            -(outcome.flaps as f32),
+                        ])
                     },
-                    &mut rng,
-                    || Duration::from_millis(50),
-                ).map(|s| s.unwrap())
-            })?;
+                ).map_err(generation_error)?
+            } else {
+                pop.run_generation_parallel(&mut rng, self.jobs, |g, r| {
+                    evaluate(g, &pop.params, r).map(|o| o.survived)
+                }).map_err(generation_error)?
+            };
+            pop = next;
 
             info!(
                 "Finished training {} generation",
                 ordinalize(&format!("{}", pop.generation()))
             );
+            info!("{}", stats);
+            for species in &stats.species {
+                debug!(
+                    "Species {}: {} members, best fitness {}",
+                    species.id, species.size, species.best_fitness
+                );
+            }
 
             if let Some(results_dir) = self.results_dir.as_ref() {
                 let file = format!("{}.gen", pop.generation());
@@ -93,6 +115,88 @@ impl Options {
     }
 }
 
+/// Converts an error from running a generation into one that can be
+/// reported.
+fn generation_error(err: GenerationError<Error>) -> Error {
+    match err {
+        GenerationError::Evaluation(err) => err,
+        err @ GenerationError::InvalidGenome(..) => format_err!("{}", err),
+    }
+}
+
+/// The number of frames between each sample of the player's position taken
+/// for novelty search.
+const SAMPLE_INTERVAL: usize = 10;
+
+/// The result of a single game.
+struct Outcome {
+    /// The time the player survived.
+    survived: f32,
+
+    /// The player's position, sampled every `SAMPLE_INTERVAL` frames.
+    trajectory: Vec<f32>,
+
+    /// The number of times the player flapped.
+    flaps: usize,
+}
+
+/// Plays a game with the given genome.
+fn evaluate<R: Rng>(
+    genome: &Genome,
+    params: &Params,
+    rng: R,
+) -> Result<Outcome, Error> {
+    let mut network = genome.build_network(params);
+    let mut trajectory = Vec::new();
+    let mut frame = 0;
+    let mut flaps = 0;
+    let (send, recv) = channel();
+    let survived = run_one(
+        poll_fn(|| -> Result<_, !> {
+            match recv.try_recv() {
+                Ok(x) => Ok(Async::Ready(Some(x))),
+                Err(TryRecvError::Empty) => Ok(Async::NotReady),
+                Err(TryRecvError::Disconnected) => Ok(Async::Ready(None)),
+            }
+        }),
+        |world| {
+            let (next_pipe_x, next_pipe_y) = world
+                .pipes
+                .iter()
+                .cloned()
+                .filter(|&(x, _)| x >= 0.5)
+                .next()
+                .unwrap_or((0.0, 0.0));
+            debug!("{} {}", next_pipe_x, next_pipe_y);
+
+            if frame % SAMPLE_INTERVAL == 0 {
+                trajectory.push(world.position);
+            }
+            frame += 1;
+
+            let out = network.calculate(&[
+                world.position,
+                next_pipe_x,
+                next_pipe_y,
+                world.velocity,
+            ]);
+
+            if out[0] > 0.5 {
+                send.send(Event::Jump)?;
+                flaps += 1;
+            }
+            Ok(())
+        },
+        rng,
+        || Duration::from_millis(50),
+    )?.unwrap();
+    Ok(Outcome {
+        survived,
+        trajectory,
+        flaps,
+    })
+}
+
 fn update_generation_file<P: AsRef<Path>>(
     path: P,
     pop: &Population,